@@ -1,4 +1,10 @@
-use clap::Parser;
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
+use soar_dl::{
+    checksum::Checksum,
+    oci::OciPlatform,
+    platform::{ApiPreference, AssetTarget, ForgeHost},
+    signature::VerifyKey,
+};
 
 #[derive(Parser)]
 #[command(
@@ -13,6 +19,9 @@ use clap::Parser;
     arg_required_else_help = true
 )]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Github project
     #[arg(required = false, long)]
     pub github: Vec<String>,
@@ -21,30 +30,69 @@ pub struct Args {
     #[arg(required = false, long)]
     pub gitlab: Vec<String>,
 
-    /// GHCR image or blob
+    /// Gitea or Forgejo project ([host/]owner/repo, defaults to codeberg.org)
+    #[arg(required = false, long)]
+    pub gitea: Vec<String>,
+
+    /// Github project to download Actions artifacts from (owner/repo[@branch])
     #[arg(required = false, long)]
-    pub ghcr: Vec<String>,
+    pub actions: Vec<String>,
+
+    /// Workflow file to take Actions artifacts from, e.g. nightly.yml
+    #[arg(required = false, long, global = true)]
+    pub workflow: Option<String>,
+
+    /// Status or conclusion of the workflow run to take Actions artifacts from
+    /// [default: success]
+    #[arg(required = false, long, global = true)]
+    pub run_status: Option<String>,
+
+    /// Self-hosted instance as platform:host, e.g. gitlab:git.corp.example or
+    /// github:github.corp.example. Tokens are read from <PLATFORM>_TOKEN_<HOST>
+    #[arg(required = false, long = "host", global = true, value_parser = parse_host)]
+    pub hosts: Vec<ForgeHost>,
+
+    /// API to fetch releases from: pkgforge-first, primary-only, pkgforge-only or a mirror
+    /// as platform=url, e.g. github=https://gh-mirror.example.com [default: pkgforge-first]
+    #[arg(required = false, long, global = true, value_parser = parse_api)]
+    pub api: Option<ApiPreference>,
+
+    /// OCI image or blob (registry[:port]/namespace/repo[:tag][@digest])
+    #[arg(required = false, long, visible_alias = "ghcr")]
+    pub oci: Vec<String>,
 
     /// Links to files
     #[arg(required = false)]
     pub links: Vec<String>,
 
+    /// File with one target per line, or a TOML/JSON manifest. Use `-` to read from stdin
+    #[arg(required = false, short, long)]
+    pub input: Option<String>,
+
     /// Regex to select the asset.
-    #[arg(required = false, short = 'r', long = "regex")]
+    #[arg(required = false, short = 'r', long = "regex", global = true)]
     pub regexes: Option<Vec<String>>,
 
     /// Glob to select the asset.
-    #[arg(required = false, short = 'g', long = "glob")]
+    #[arg(required = false, short = 'g', long = "glob", global = true)]
     pub globs: Option<Vec<String>>,
 
     /// Check if the asset contains given string
-    #[arg(required = false, short, long = "match")]
+    #[arg(required = false, short, long = "match", global = true)]
     pub match_keywords: Option<Vec<String>>,
 
     /// Check if the asset contains given string
-    #[arg(required = false, short, long = "exclude")]
+    #[arg(required = false, short, long = "exclude", global = true)]
     pub exclude_keywords: Option<Vec<String>>,
 
+    /// Pick the release asset that best matches the host OS, architecture and libc
+    #[arg(required = false, long, global = true)]
+    pub auto: bool,
+
+    /// Target triple to match release assets against instead of the host (implies --auto)
+    #[arg(required = false, long, global = true, value_parser = parse_target)]
+    pub target: Option<AssetTarget>,
+
     /// Skip all prompts and use first
     #[arg(required = false, short, long)]
     pub yes: bool,
@@ -53,16 +101,16 @@ pub struct Args {
     #[arg(required = false, short, long)]
     pub output: Option<String>,
 
-    /// GHCR concurrency
+    /// Number of OCI layers to pull concurrently [default: layer count, up to 4]
     #[arg(required = false, short, long)]
     pub concurrency: Option<u64>,
 
-    /// GHCR API to use
-    #[arg(required = false, long)]
-    pub ghcr_api: Option<String>,
+    /// OCI registry API to use, overrides the one derived from the reference
+    #[arg(required = false, long, visible_alias = "ghcr-api")]
+    pub oci_api: Option<String>,
 
     /// Whether to use exact case matching for keywords
-    #[arg(required = false, long)]
+    #[arg(required = false, long, global = true)]
     pub exact_case: bool,
 
     /// Extract supported archive automatically
@@ -74,19 +122,19 @@ pub struct Args {
     pub extract_dir: Option<String>,
 
     /// Quiet mode
-    #[arg(required = false, long, short)]
+    #[arg(required = false, long, short, global = true)]
     pub quiet: bool,
 
     /// Set proxy
-    #[arg(required = false, long)]
+    #[arg(required = false, long, global = true)]
     pub proxy: Option<String>,
 
     /// Set request headers
-    #[arg(required = false, long, short = 'H')]
+    #[arg(required = false, long, short = 'H', global = true)]
     pub header: Option<Vec<String>>,
 
     /// Set user agent
-    #[arg(required = false, long, short = 'A')]
+    #[arg(required = false, long, short = 'A', global = true)]
     pub user_agent: Option<String>,
 
     /// Skip existing download with same file
@@ -96,4 +144,93 @@ pub struct Args {
     /// Overwrite existing download with same file
     #[arg(required = false, long)]
     pub force_overwrite: bool,
+
+    /// Expected checksum of the file (sha256:<hex>, sha512:<hex> or blake3:<hex>)
+    #[arg(required = false, long, value_parser = parse_checksum)]
+    pub checksum: Option<Checksum>,
+
+    /// Skip verifying release assets against checksum files published in the release
+    #[arg(required = false, long)]
+    pub skip_checksum: bool,
+
+    /// Split direct and release downloads into N concurrent range requests (at most 16)
+    #[arg(required = false, long)]
+    pub segments: Option<u64>,
+
+    /// Number of times to retry failed requests
+    #[arg(required = false, long, default_value_t = 3, global = true)]
+    pub retries: u32,
+
+    /// Public key (minisign, cosign or GPG) or path to it, used to verify release asset signatures
+    #[arg(required = false, long, value_parser = parse_verify_key)]
+    pub verify_key: Option<VerifyKey>,
+
+    /// Platform to select from multi-arch OCI images (os/arch[/variant]). Defaults to host
+    #[arg(required = false, long, value_parser = parse_platform)]
+    pub platform: Option<OciPlatform>,
+
+    /// Number of targets to download concurrently
+    #[arg(required = false, short, long, default_value_t = 4)]
+    pub jobs: usize,
+
+    /// Print newline-delimited JSON events to stdout
+    #[arg(required = false, long, global = true)]
+    pub json: bool,
+
+    /// Resolve targets and show what would be downloaded, without writing anything
+    #[arg(required = false, long)]
+    pub dry_run: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List releases and their assets without downloading. Asset filters apply
+    List {
+        /// GitHub, GitLab or Gitea projects (github:owner/repo[@tag], gitlab:project[@tag],
+        /// gitea:host/owner/repo[@tag] or URL), or actions:owner/repo[@branch] to list
+        /// workflow runs and their artifacts
+        #[arg(required = true)]
+        targets: Vec<String>,
+    },
+}
+
+impl Args {
+    /// Checks combinations of arguments that clap can't express.
+    pub fn validate(&self) -> Result<(), clap::Error> {
+        if self.json && self.output.as_deref() == Some("-") {
+            return Err(Args::command().error(
+                ErrorKind::ArgumentConflict,
+                "--json can't be used with '--output -', both write to stdout",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn parse_checksum(value: &str) -> Result<Checksum, String> {
+    Checksum::parse(value).ok_or_else(|| format!("invalid checksum '{}'", value))
+}
+
+fn parse_platform(value: &str) -> Result<OciPlatform, String> {
+    OciPlatform::parse(value).ok_or_else(|| format!("invalid platform '{}'", value))
+}
+
+fn parse_target(value: &str) -> Result<AssetTarget, String> {
+    AssetTarget::parse(value).ok_or_else(|| format!("invalid target triple '{}'", value))
+}
+
+fn parse_host(value: &str) -> Result<ForgeHost, String> {
+    ForgeHost::parse(value).ok_or_else(|| format!("invalid host '{}'", value))
+}
+
+fn parse_api(value: &str) -> Result<ApiPreference, String> {
+    ApiPreference::parse(value).ok_or_else(|| format!("invalid API '{}'", value))
+}
+
+fn parse_verify_key(value: &str) -> Result<VerifyKey, String> {
+    let content = match std::fs::read_to_string(value) {
+        Ok(content) => content,
+        Err(_) => value.to_string(),
+    };
+    VerifyKey::parse(&content).map_err(|err| err.to_string())
 }
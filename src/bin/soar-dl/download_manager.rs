@@ -1,253 +1,894 @@
-use std::{sync::Arc, thread, time::Duration};
+use std::{collections::HashSet, fmt::Display, path::Path, sync::Arc, time::Instant};
 
-use indicatif::HumanBytes;
+use indicatif::{HumanBytes, MultiProgress};
 use regex::Regex;
-use reqwest::StatusCode;
 use serde::Deserialize;
 use soar_dl::{
-    downloader::{DownloadOptions, DownloadState, Downloader, OciDownloadOptions, OciDownloader},
+    checksum::{Checksum, ChecksumHasher},
+    downloader::{
+        DownloadOptions, DownloadPlan, DownloadState, Downloader, LayerStatus, OciDownloadOptions,
+        OciDownloader, SkipReason,
+    },
     error::{DownloadError, PlatformError},
-    github::{Github, GithubAsset, GithubRelease},
+    gitea::{Gitea, GiteaAsset, GiteaRelease},
+    github::{Github, GithubAsset, GithubRelease, WorkflowRunFilter},
     gitlab::{Gitlab, GitlabAsset, GitlabRelease},
     platform::{
-        PlatformDownloadOptions, PlatformUrl, Release, ReleaseAsset, ReleaseHandler,
-        ReleasePlatform,
+        filter_assets, AssetTarget, ForgeHost, PlatformDownloadOptions, PlatformUrl, Release,
+        ReleaseAsset, ReleaseHandler, ReleasePlatform, RepoSource,
     },
-    utils::get_file_mode,
+    retry::RetryPolicy,
+    scheduler::Scheduler,
+    utils::{get_file_mode, PromptCallback},
+};
+use tokio::{runtime::Handle, sync::Mutex};
+
+use crate::{
+    cli::Args,
+    error,
+    events::{self, ErrorInfo, Event, ListedAsset},
+    info, manifest,
+    progress::{create_progress_bar, handle_progress},
 };
 
-use crate::{cli::Args, error, info};
+type ProgressCallback = Arc<dyn Fn(DownloadState) + Send + Sync>;
+
+#[derive(Clone, Debug)]
+pub enum Target {
+    Github(String),
+    Gitlab(String),
+    Gitea(String),
+    /// GitHub Actions artifacts, `owner/repo[@branch]`
+    Actions(String),
+    Oci(String),
+    Direct(String),
+}
+
+/// Per-target overrides of the command line options.
+#[derive(Clone, Debug, Default)]
+pub struct JobOptions {
+    pub output: Option<String>,
+    pub regexes: Option<Vec<String>>,
+    pub globs: Option<Vec<String>>,
+    pub match_keywords: Option<Vec<String>>,
+    pub exclude_keywords: Option<Vec<String>>,
+    pub checksum: Option<Checksum>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Job {
+    pub target: Target,
+    pub options: JobOptions,
+}
+
+pub struct Downloaded {
+    pub path: String,
+    pub checksum: Option<String>,
+}
+
+pub struct JobResult {
+    pub target: String,
+    pub result: Result<Vec<Downloaded>, PlatformError>,
+}
 
 pub struct DownloadManager {
     args: Args,
-    progress_callback: Arc<dyn Fn(DownloadState) + Send + Sync>,
+    progress: MultiProgress,
+    prompt_lock: Arc<Mutex<()>>,
+}
+
+impl Target {
+    /// Parses `github:<project>`, `gitlab:<project>`, `actions:<project>`,
+    /// `oci:<reference>` or anything
+    /// [`PlatformUrl::parse_with_hosts`] accepts.
+    pub fn parse(value: &str, hosts: &[ForgeHost]) -> Result<Self, PlatformError> {
+        if let Some(project) = value.strip_prefix("github:") {
+            return Ok(Target::Github(project.to_string()));
+        }
+        if let Some(project) = value.strip_prefix("gitlab:") {
+            return Ok(Target::Gitlab(project.to_string()));
+        }
+        if let Some(project) = value.strip_prefix("actions:") {
+            return Ok(Target::Actions(project.to_string()));
+        }
+        if let Some(reference) = value.strip_prefix("oci:").filter(|r| !r.starts_with("//")) {
+            return Ok(Target::Oci(reference.to_string()));
+        }
+
+        Ok(match PlatformUrl::parse_with_hosts(value, hosts)? {
+            PlatformUrl::DirectUrl(_) => Target::Direct(value.to_string()),
+            PlatformUrl::Github(project) => Target::Github(project),
+            PlatformUrl::Gitlab(project) => Target::Gitlab(project),
+            PlatformUrl::Gitea(project) => Target::Gitea(project),
+            PlatformUrl::Oci(reference) => Target::Oci(reference),
+        })
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Target::Github(project) => write!(f, "github:{}", project),
+            Target::Gitlab(project) => write!(f, "gitlab:{}", project),
+            Target::Gitea(project) => write!(f, "gitea:{}", project),
+            Target::Actions(project) => write!(f, "actions:{}", project),
+            Target::Oci(reference) => write!(f, "oci:{}", reference),
+            Target::Direct(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+impl From<Target> for Job {
+    fn from(target: Target) -> Self {
+        Self {
+            target,
+            options: JobOptions::default(),
+        }
+    }
+}
+
+impl Display for Job {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.target.fmt(f)
+    }
 }
 
 impl DownloadManager {
-    pub fn new(args: Args, progress_callback: Arc<dyn Fn(DownloadState) + Send + Sync>) -> Self {
+    pub fn new(args: Args, progress: MultiProgress) -> Self {
         Self {
             args,
-            progress_callback,
+            progress,
+            prompt_lock: Arc::new(Mutex::new(())),
         }
     }
 
-    pub async fn execute(&self) {
-        let _ = self.handle_github_downloads().await;
-        let _ = self.handle_oci_downloads().await;
-        let _ = self.handle_gitlab_downloads().await;
-        let _ = self.handle_direct_downloads().await;
+    pub async fn execute(self: &Arc<Self>) -> Vec<JobResult> {
+        let mut results = Vec::new();
+        let mut jobs = Vec::new();
+
+        jobs.extend(self.args.github.iter().cloned().map(Target::Github));
+        jobs.extend(self.args.oci.iter().cloned().map(Target::Oci));
+        jobs.extend(self.args.gitlab.iter().cloned().map(Target::Gitlab));
+        jobs.extend(self.args.gitea.iter().cloned().map(Target::Gitea));
+        jobs.extend(self.args.actions.iter().cloned().map(Target::Actions));
+        let mut jobs = jobs.into_iter().map(Job::from).collect::<Vec<_>>();
+        for link in &self.args.links {
+            match Target::parse(link, &self.args.hosts) {
+                Ok(target) => jobs.push(target.into()),
+                Err(err) => {
+                    error!("Error parsing URL '{}' : {}", link, err);
+                    events::emit(&Event::Error {
+                        target: link,
+                        error: ErrorInfo::from(&err),
+                    });
+                    results.push(JobResult {
+                        target: link.clone(),
+                        result: Err(err),
+                    });
+                }
+            }
+        }
+
+        if let Some(ref input) = self.args.input {
+            let entries = manifest::load(input, &self.args.hosts).and_then(|entries| {
+                let stdout = entries
+                    .iter()
+                    .any(|job| job.options.output.as_deref() == Some("-"));
+                if self.args.json && stdout {
+                    return Err("output '-' can't be used with --json".to_string());
+                }
+                Ok(entries)
+            });
+            match entries {
+                Ok(entries) => jobs.extend(entries),
+                Err(err) => {
+                    error!("Invalid input file {}: {}", input, err);
+                    let err = PlatformError::InvalidInput(input.clone());
+                    events::emit(&Event::Error {
+                        target: input,
+                        error: ErrorInfo::from(&err),
+                    });
+                    return vec![JobResult {
+                        target: input.clone(),
+                        result: Err(err),
+                    }];
+                }
+            }
+        }
+
+        if jobs.is_empty() && results.is_empty() {
+            error!("No targets to download");
+        }
+
+        // jobs writing to stdout or to the same file can't run side by side
+        let concurrency = if shares_output(&jobs, self.args.output.as_deref()) {
+            1
+        } else {
+            self.args.jobs
+        };
+        let scheduler = Scheduler::new(concurrency);
+        let handles = jobs
+            .into_iter()
+            .map(|job| {
+                let manager = self.clone();
+                let target = job.to_string();
+                let handle = scheduler.spawn(async move { manager.run_job(&job).await });
+                (target, handle)
+            })
+            .collect::<Vec<_>>();
+
+        for (target, handle) in handles {
+            let result = handle.await.unwrap_or_else(|err| {
+                Err(PlatformError::DownloadError(DownloadError::TaskError(
+                    err.to_string(),
+                )))
+            });
+            results.push(JobResult { target, result });
+        }
+
+        if results.len() > 1 {
+            print_summary(&results);
+        }
+        let failed = results.iter().filter(|r| r.result.is_err()).count();
+        events::emit(&Event::Summary {
+            succeeded: results.len() - failed,
+            failed,
+        });
+        results
     }
 
-    fn create_regexes(&self) -> Vec<Regex> {
-        self.args
+    pub async fn list(&self, targets: &[String]) -> Vec<JobResult> {
+        let mut results = Vec::new();
+        for value in targets {
+            if targets.len() > 1 && !events::is_json() {
+                println!("{}:", value);
+            }
+
+            let result = match Target::parse(value, &self.args.hosts) {
+                Ok(Target::Github(project)) => {
+                    let (handler, project) = self.release_handler::<Github>(&project);
+                    self.list_releases::<Github, GithubRelease, GithubAsset>(
+                        value, &handler, project,
+                    )
+                    .await
+                }
+                Ok(Target::Gitlab(project)) => {
+                    let (handler, project) = self.release_handler::<Gitlab>(&project);
+                    self.list_releases::<Gitlab, GitlabRelease, GitlabAsset>(
+                        value, &handler, project,
+                    )
+                    .await
+                }
+                Ok(Target::Gitea(project)) => {
+                    let (handler, project) = self.release_handler::<Gitea>(&project);
+                    self.list_releases::<Gitea, GiteaRelease, GiteaAsset>(value, &handler, project)
+                        .await
+                }
+                Ok(Target::Actions(project)) => {
+                    let (handler, project) = self.release_handler::<Github>(&project);
+                    self.list_workflow_runs(value, &handler, project).await
+                }
+                Ok(_) => Err(PlatformError::InvalidInput(value.clone())),
+                Err(err) => Err(err),
+            };
+            if let Err(ref err) = result {
+                error!("{}: {}", value, err);
+                events::emit(&Event::Error {
+                    target: value,
+                    error: ErrorInfo::from(err),
+                });
+            }
+
+            results.push(JobResult {
+                target: value.clone(),
+                result: result.map(|_| Vec::new()),
+            });
+        }
+        results
+    }
+
+    async fn list_releases<P: ReleasePlatform, R, A>(
+        &self,
+        target: &str,
+        handler: &ReleaseHandler<'_, P>,
+        project: &str,
+    ) -> Result<(), PlatformError>
+    where
+        R: Release<A> + for<'de> Deserialize<'de>,
+        A: ReleaseAsset,
+    {
+        let (project, tag) = split_tag(project);
+        let options = self.create_platform_options(
+            tag.map(String::from),
+            &self.job_options(&JobOptions::default()),
+            Arc::new(|_| {}),
+        )?;
+
+        for release in handler.list_releases::<R, A>(project, &options).await? {
+            if events::is_json() {
+                events::emit(&Event::ListedRelease {
+                    target,
+                    name: &release.name,
+                    tag: &release.tag_name,
+                    published_at: &release.published_at,
+                    prerelease: release.prerelease,
+                    assets: release
+                        .assets
+                        .iter()
+                        .map(|asset| ListedAsset {
+                            name: &asset.name,
+                            url: &asset.download_url,
+                            size: asset.size,
+                        })
+                        .collect(),
+                });
+                continue;
+            }
+
+            println!(
+                "{}  {}  {}{}",
+                release.tag_name,
+                release.name,
+                release.published_at,
+                if release.prerelease {
+                    "  [prerelease]"
+                } else {
+                    ""
+                }
+            );
+            for asset in &release.assets {
+                let size = asset
+                    .size
+                    .map(|s| format!(" ({})", HumanBytes(s)))
+                    .unwrap_or_default();
+                println!("    {}{}", asset.name, size);
+            }
+        }
+        Ok(())
+    }
+
+    async fn list_workflow_runs(
+        &self,
+        target: &str,
+        handler: &ReleaseHandler<'_, Github>,
+        project: &str,
+    ) -> Result<(), PlatformError> {
+        let (project, branch) = split_tag(project);
+        let filter = self.workflow_run_filter(branch);
+        let options = self.create_platform_options(
+            None,
+            &self.job_options(&JobOptions::default()),
+            Arc::new(|_| {}),
+        )?;
+
+        for run in handler.fetch_workflow_runs(project, &filter).await? {
+            let artifacts = handler.fetch_artifacts(project, run.id).await?;
+            let artifacts = filter_assets(artifacts, &options).unwrap_or_default();
+            if events::is_json() {
+                events::emit(&Event::ListedRun {
+                    target,
+                    run_number: run.run_number,
+                    name: run.name.as_deref(),
+                    branch: run.head_branch.as_deref(),
+                    sha: &run.head_sha,
+                    created_at: &run.created_at,
+                    artifacts: artifacts
+                        .iter()
+                        .map(|artifact| ListedAsset {
+                            name: &artifact.name,
+                            url: artifact.download_url(),
+                            size: Some(artifact.size_in_bytes),
+                        })
+                        .collect(),
+                });
+                continue;
+            }
+
+            println!(
+                "#{}  {}  {}  {}  {}",
+                run.run_number,
+                run.name.as_deref().unwrap_or_default(),
+                run.head_branch.as_deref().unwrap_or_default(),
+                short_sha(&run.head_sha),
+                run.created_at
+            );
+            for artifact in artifacts {
+                println!(
+                    "    {} ({})",
+                    artifact.name,
+                    HumanBytes(artifact.size_in_bytes)
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn run_job(&self, job: &Job) -> Result<Vec<Downloaded>, PlatformError> {
+        let target = job.to_string();
+        let started = Instant::now();
+        events::emit(&Event::Start { target: &target });
+
+        let progress_bar = self.progress.add(create_progress_bar());
+        progress_bar.set_prefix(target.clone());
+        let progress_callback: ProgressCallback = {
+            let progress_bar = progress_bar.clone();
+            Arc::new(move |state| handle_progress(state, &progress_bar))
+        };
+
+        let result = self.download_target(job, &target, progress_callback).await;
+
+        match result {
+            Ok(_) if self.args.dry_run => progress_bar.finish_and_clear(),
+            Ok(ref downloaded) => {
+                if !progress_bar.is_finished() {
+                    progress_bar.finish();
+                }
+                let duration_ms = started.elapsed().as_millis();
+                for file in downloaded {
+                    emit_complete(&target, file, duration_ms).await;
+                }
+            }
+            Err(ref err) => {
+                progress_bar.finish_and_clear();
+                error!("{}: {}", job, err);
+                events::emit(&Event::Error {
+                    target: &target,
+                    error: ErrorInfo::from(err),
+                });
+            }
+        }
+        result
+    }
+
+    async fn download_target(
+        &self,
+        job: &Job,
+        target: &str,
+        progress_callback: ProgressCallback,
+    ) -> Result<Vec<Downloaded>, PlatformError> {
+        let options = self.job_options(&job.options);
+        match job.target {
+            Target::Github(ref project) => {
+                info!("Fetching releases from GitHub: {}", project);
+                let (handler, project) = self.release_handler::<Github>(project);
+                self.handle_platform_download::<Github, GithubRelease, GithubAsset>(
+                    &handler,
+                    target,
+                    project,
+                    &options,
+                    progress_callback,
+                )
+                .await
+            }
+            Target::Gitlab(ref project) => {
+                info!("Fetching releases from GitLab: {}", project);
+                let (handler, project) = self.release_handler::<Gitlab>(project);
+                self.handle_platform_download::<Gitlab, GitlabRelease, GitlabAsset>(
+                    &handler,
+                    target,
+                    project,
+                    &options,
+                    progress_callback,
+                )
+                .await
+            }
+            Target::Gitea(ref project) => {
+                info!("Fetching releases from Gitea: {}", project);
+                let (handler, project) = self.release_handler::<Gitea>(project);
+                self.handle_platform_download::<Gitea, GiteaRelease, GiteaAsset>(
+                    &handler,
+                    target,
+                    project,
+                    &options,
+                    progress_callback,
+                )
+                .await
+            }
+            Target::Actions(ref project) => {
+                info!("Fetching workflow runs from GitHub: {}", project);
+                let (handler, project) = self.release_handler::<Github>(project);
+                self.handle_actions_download(&handler, target, project, &options, progress_callback)
+                    .await
+            }
+            Target::Oci(ref reference) => {
+                info!("Downloading using OCI reference: {}", reference);
+                self.handle_oci_download(target, reference, &options, progress_callback)
+                    .await
+            }
+            Target::Direct(ref url) => {
+                info!("Downloading using direct link: {}", url);
+                self.handle_direct_download(target, url, &options, progress_callback)
+                    .await
+            }
+        }
+    }
+
+    fn job_options(&self, overrides: &JobOptions) -> JobOptions {
+        let args = &self.args;
+        JobOptions {
+            output: overrides.output.clone().or_else(|| args.output.clone()),
+            regexes: overrides.regexes.clone().or_else(|| args.regexes.clone()),
+            globs: overrides.globs.clone().or_else(|| args.globs.clone()),
+            match_keywords: overrides
+                .match_keywords
+                .clone()
+                .or_else(|| args.match_keywords.clone()),
+            exclude_keywords: overrides
+                .exclude_keywords
+                .clone()
+                .or_else(|| args.exclude_keywords.clone()),
+            checksum: overrides.checksum.clone().or_else(|| args.checksum.clone()),
+        }
+    }
+
+    fn create_regexes(&self, options: &JobOptions) -> Result<Vec<Regex>, PlatformError> {
+        options
             .regexes
-            .clone()
-            .map(|patterns| {
-                patterns
-                    .iter()
-                    .map(|pattern| Regex::new(pattern))
-                    .collect::<Result<Vec<Regex>, regex::Error>>()
+            .iter()
+            .flatten()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|_| PlatformError::InvalidInput(pattern.clone()))
             })
-            .transpose()
-            .unwrap()
-            .unwrap_or_default()
+            .collect()
+    }
+
+    /// Handler for the instance `project` is on, along with the project on it.
+    fn release_handler<'p, P: ReleasePlatform>(
+        &self,
+        project: &'p str,
+    ) -> (ReleaseHandler<'_, P>, &'p str) {
+        let handler = ReleaseHandler::<P>::new().with_retry(self.create_retry_policy());
+        match P::split_instance(project, &self.args.hosts) {
+            (Some(instance_url), project) => (handler.with_instance(&instance_url), project),
+            (None, project) => (handler, project),
+        }
+    }
+
+    fn create_retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.args.retries + 1,
+            ..Default::default()
+        }
     }
 
-    fn create_platform_options(&self, tag: Option<String>) -> PlatformDownloadOptions {
-        let regexes = self.create_regexes();
-        PlatformDownloadOptions {
-            output_path: self.args.output.clone(),
-            progress_callback: Some(self.progress_callback.clone()),
+    fn create_platform_options(
+        &self,
+        tag: Option<String>,
+        options: &JobOptions,
+        progress_callback: ProgressCallback,
+    ) -> Result<PlatformDownloadOptions, PlatformError> {
+        let regexes = self.create_regexes(options)?;
+        Ok(PlatformDownloadOptions {
+            output_path: options.output.clone(),
+            progress_callback: Some(progress_callback),
             tag,
             regexes,
-            globs: self.args.globs.clone().unwrap_or_default(),
-            match_keywords: self.args.match_keywords.clone().unwrap_or_default(),
-            exclude_keywords: self.args.exclude_keywords.clone().unwrap_or_default(),
-            exact_case: false,
+            globs: options.globs.clone().unwrap_or_default(),
+            match_keywords: options.match_keywords.clone().unwrap_or_default(),
+            exclude_keywords: options.exclude_keywords.clone().unwrap_or_default(),
+            exact_case: self.args.exact_case,
             extract_archive: self.args.extract,
             extract_dir: self.args.extract_dir.clone(),
             file_mode: get_file_mode(self.args.skip_existing, self.args.force_overwrite),
-            prompt: None,
+            prompt: Some(self.overwrite_prompt()),
+            checksum: options.checksum.clone(),
+            skip_checksum: self.args.skip_checksum,
+            segments: self.args.segments,
+            retry: Some(self.create_retry_policy()),
+            verify_key: self.args.verify_key.clone(),
+            signature: None,
+            dry_run: self.args.dry_run,
+            api: self.args.api.clone().unwrap_or_default(),
+            auto: self.asset_target(),
+        })
+    }
+
+    fn workflow_run_filter(&self, branch: Option<&str>) -> WorkflowRunFilter {
+        WorkflowRunFilter {
+            workflow: self.args.workflow.clone(),
+            branch: branch.map(String::from),
+            status: self.args.run_status.clone(),
         }
     }
 
+    fn asset_target(&self) -> Option<AssetTarget> {
+        self.args
+            .target
+            .clone()
+            .or_else(|| self.args.auto.then(AssetTarget::host))
+    }
+
     async fn handle_platform_download<P: ReleasePlatform, R, A>(
         &self,
         handler: &ReleaseHandler<'_, P>,
+        target: &str,
         project: &str,
-    ) -> Result<(), PlatformError>
+        job_options: &JobOptions,
+        progress_callback: ProgressCallback,
+    ) -> Result<Vec<Downloaded>, PlatformError>
     where
         R: Release<A> + for<'de> Deserialize<'de>,
         A: ReleaseAsset + Clone,
     {
-        let (project, tag) = match project.trim().split_once('@') {
-            Some((proj, tag)) if !tag.trim().is_empty() => (proj, Some(tag.trim())),
-            _ => (project.trim_end_matches('@'), None),
-        };
+        if let (project, Some(source)) = RepoSource::split(project) {
+            return self
+                .handle_source_download::<P, R, A>(
+                    handler,
+                    target,
+                    project,
+                    &source,
+                    job_options,
+                    progress_callback,
+                )
+                .await;
+        }
 
-        let options = self.create_platform_options(tag.map(String::from));
-        let releases = handler.fetch_releases::<R>(project, tag).await?;
-        let assets = handler.filter_releases(&releases, &options).await?;
+        let (project, tag) = split_tag(project);
 
-        let selected_asset = self.select_asset(&assets)?;
+        let mut options =
+            self.create_platform_options(tag.map(String::from), job_options, progress_callback)?;
+        let releases = handler
+            .fetch_matching_releases::<R, A>(project, &options)
+            .await?;
+        if let Ok(release) = handler.select_release(&releases, &options) {
+            events::emit(&Event::Release {
+                target,
+                name: release.name(),
+                tag: release.tag_name(),
+            });
+        }
+        let assets = handler.filter_releases(&releases, &options).await?;
 
-        info!("Downloading asset from {}", selected_asset.download_url());
-        handler.download(&selected_asset, options.clone()).await?;
-        Ok(())
-    }
+        let selected_asset = self.select_asset(&assets).await?;
+        events::emit(&Event::Asset {
+            target,
+            name: selected_asset.name(),
+            url: selected_asset.download_url(),
+            size: selected_asset.size(),
+        });
 
-    async fn handle_github_downloads(&self) -> Result<(), PlatformError> {
-        if self.args.github.is_empty() {
-            return Ok(());
+        if options.dry_run {
+            let plan = handler.plan(&selected_asset, &options).await?;
+            report_plan(target, &plan);
+            return Ok(vec![Downloaded {
+                path: plan.path,
+                checksum: options.checksum.as_ref().map(ToString::to_string),
+            }]);
         }
 
-        let handler = ReleaseHandler::<Github>::new();
-        for project in &self.args.github {
-            info!("Fetching releases from GitHub: {}", project);
-            if let Err(e) = self
-                .handle_platform_download::<Github, GithubRelease, GithubAsset>(&handler, project)
+        // looked up here as well to report it, `download` only does it if it's not set
+        if options.checksum.is_none() && !options.skip_checksum {
+            match handler
+                .fetch_checksum(&releases, &selected_asset, &options)
                 .await
             {
-                error!("{}", e);
+                Ok(checksum) => options.checksum = checksum,
+                Err(err @ PlatformError::ChecksumNotFound { .. }) => {
+                    info!("Warning: {}, skipping checksum verification", err);
+                    options.skip_checksum = true;
+                }
+                Err(err) => return Err(err),
+            }
+            if let Some(ref checksum) = options.checksum {
+                info!("Found checksum {}", checksum);
             }
         }
-        Ok(())
+
+        info!("Downloading asset from {}", selected_asset.download_url());
+        let checksum = options.checksum.as_ref().map(ToString::to_string);
+        let path = handler
+            .download(&releases, &selected_asset, options)
+            .await?;
+        Ok(vec![Downloaded { path, checksum }])
     }
 
-    async fn handle_gitlab_downloads(&self) -> Result<(), PlatformError> {
-        if self.args.gitlab.is_empty() {
-            return Ok(());
+    async fn handle_source_download<P: ReleasePlatform, R, A>(
+        &self,
+        handler: &ReleaseHandler<'_, P>,
+        target: &str,
+        project: &str,
+        source: &RepoSource,
+        job_options: &JobOptions,
+        progress_callback: ProgressCallback,
+    ) -> Result<Vec<Downloaded>, PlatformError>
+    where
+        R: Release<A> + for<'de> Deserialize<'de>,
+        A: ReleaseAsset,
+    {
+        let (project, tag) = split_tag(project);
+
+        let options =
+            self.create_platform_options(tag.map(String::from), job_options, progress_callback)?;
+        let git_ref = handler.resolve_ref::<R, A>(project, &options).await?;
+        let checksum = options.checksum.as_ref().map(ToString::to_string);
+
+        if options.dry_run {
+            let plan = handler
+                .plan_source(project, &git_ref, source, &options)
+                .await?;
+            report_plan(target, &plan);
+            return Ok(vec![Downloaded {
+                path: plan.path,
+                checksum,
+            }]);
         }
 
-        let handler = ReleaseHandler::<Gitlab>::new();
-        for project in &self.args.gitlab {
-            info!("Fetching releases from GitLab: {}", project);
-            if let Err(e) = self
-                .handle_platform_download::<Gitlab, GitlabRelease, GitlabAsset>(&handler, project)
-                .await
-            {
-                error!("{}", e);
+        info!("Downloading {} at {}", source, git_ref);
+        let path = handler
+            .download_source(project, &git_ref, source, options)
+            .await?;
+        Ok(vec![Downloaded { path, checksum }])
+    }
+
+    async fn handle_actions_download(
+        &self,
+        handler: &ReleaseHandler<'_, Github>,
+        target: &str,
+        project: &str,
+        job_options: &JobOptions,
+        progress_callback: ProgressCallback,
+    ) -> Result<Vec<Downloaded>, PlatformError> {
+        // GitHub doesn't serve artifacts without a token, not even for public repositories
+        if !handler.has_token() {
+            return Err(PlatformError::InvalidInput(format!(
+                "Downloading Actions artifacts without {} set",
+                handler.token_var()
+            )));
+        }
+
+        let (project, branch) = split_tag(project);
+        let filter = self.workflow_run_filter(branch);
+
+        let mut options = self.create_platform_options(None, job_options, progress_callback)?;
+        let runs = handler.fetch_workflow_runs(project, &filter).await?;
+
+        // the newest runs may not have produced the artifact, e.g. if it's built on a schedule
+        let mut result = Err(PlatformError::NoWorkflowRun {
+            branch: filter.branch,
+        });
+        for run in &runs {
+            let artifacts = handler.fetch_artifacts(project, run.id).await?;
+            result = filter_assets(artifacts, &options).map(|artifacts| (run, artifacts));
+            match result {
+                Err(PlatformError::NoMatchingAssets { .. }) => continue,
+                _ => break,
             }
         }
-        Ok(())
+        let (run, artifacts) = result?;
+        info!(
+            "Using workflow run #{} at {} from {}",
+            run.run_number,
+            short_sha(&run.head_sha),
+            run.created_at
+        );
+
+        let artifact = self.select_asset(&artifacts).await?;
+        events::emit(&Event::Asset {
+            target,
+            name: artifact.name(),
+            url: artifact.download_url(),
+            size: artifact.size(),
+        });
+
+        if options.checksum.is_none() && !options.skip_checksum {
+            options.checksum = artifact.digest.as_deref().and_then(Checksum::parse);
+        }
+
+        if options.dry_run {
+            let plan = handler.plan(&artifact, &options).await?;
+            report_plan(target, &plan);
+            return Ok(vec![Downloaded {
+                path: plan.path,
+                checksum: options.checksum.as_ref().map(ToString::to_string),
+            }]);
+        }
+
+        info!("Downloading artifact {}", artifact.name);
+        let checksum = options.checksum.as_ref().map(ToString::to_string);
+        let path = handler.download_asset(&artifact, options).await?;
+        Ok(vec![Downloaded { path, checksum }])
     }
 
-    async fn handle_oci_download(&self, reference: &str) -> Result<(), PlatformError> {
-        let regexes = self.create_regexes();
+    async fn handle_oci_download(
+        &self,
+        target: &str,
+        reference: &str,
+        job_options: &JobOptions,
+        progress_callback: ProgressCallback,
+    ) -> Result<Vec<Downloaded>, PlatformError> {
+        if self.args.verify_key.is_some() {
+            return Err(PlatformError::InvalidInput(
+                "Signature verification of OCI downloads".to_string(),
+            ));
+        }
+
+        let regexes = self.create_regexes(job_options)?;
         let options = OciDownloadOptions {
             url: reference.to_string(),
-            concurrency: self.args.concurrency.clone(),
-            output_path: self.args.output.clone(),
-            progress_callback: Some(self.progress_callback.clone()),
-            api: self.args.ghcr_api.clone(),
+            concurrency: self.args.concurrency,
+            output_path: job_options.output.clone(),
+            progress_callback: Some(progress_callback),
+            api: self.args.oci_api.clone(),
             regexes,
-            globs: self.args.globs.clone().unwrap_or_default(),
-            match_keywords: self.args.match_keywords.clone().unwrap_or_default(),
-            exclude_keywords: self.args.exclude_keywords.clone().unwrap_or_default(),
+            globs: job_options.globs.clone().unwrap_or_default(),
+            match_keywords: job_options.match_keywords.clone().unwrap_or_default(),
+            exclude_keywords: job_options.exclude_keywords.clone().unwrap_or_default(),
             exact_case: self.args.exact_case,
             file_mode: get_file_mode(self.args.skip_existing, self.args.force_overwrite),
+            retry: Some(self.create_retry_policy()),
+            platform: self.args.platform.clone(),
+            dry_run: self.args.dry_run,
         };
         let mut downloader = OciDownloader::new(options);
-        let mut retries = 0;
-        loop {
-            if retries > 5 {
-                error!("Max retries exhausted. Aborting.");
-                break;
-            }
-            match downloader.download_oci().await {
-                Ok(_) => break,
-                Err(
-                    DownloadError::ResourceError {
-                        status: StatusCode::TOO_MANY_REQUESTS,
-                        ..
-                    }
-                    | DownloadError::ChunkError,
-                ) => thread::sleep(Duration::from_secs(5)),
-                Err(err) => {
-                    error!("{}", err);
-                    break;
-                }
-            };
-            retries += 1;
-        }
+        let report = downloader.download_oci().await?;
 
-        Ok(())
+        Ok(report
+            .layers
+            .into_iter()
+            .filter(|layer| match layer.status {
+                LayerStatus::Failed(_) => false,
+                LayerStatus::Skipped(reason @ SkipReason::Untitled) => {
+                    info!("Skipping layer {}: {}", layer.digest, reason);
+                    false
+                }
+                _ => true,
+            })
+            .inspect(|layer| {
+                if let LayerStatus::Planned(ref plan) = layer.status {
+                    report_plan(target, plan);
+                }
+            })
+            .map(|layer| Downloaded {
+                path: layer.path.display().to_string(),
+                checksum: Some(layer.digest),
+            })
+            .collect())
     }
 
-    async fn handle_oci_downloads(&self) -> Result<(), PlatformError> {
-        if self.args.ghcr.is_empty() {
-            return Ok(());
-        }
-
-        for reference in &self.args.ghcr {
-            info!("Downloading using OCI reference: {}", reference);
-
-            self.handle_oci_download(reference).await?;
+    async fn handle_direct_download(
+        &self,
+        target: &str,
+        url: &str,
+        job_options: &JobOptions,
+        progress_callback: ProgressCallback,
+    ) -> Result<Vec<Downloaded>, PlatformError> {
+        if self.args.verify_key.is_some() {
+            return Err(PlatformError::InvalidInput(
+                "Signature verification of direct downloads".to_string(),
+            ));
         }
-        Ok(())
-    }
 
-    async fn handle_direct_downloads(&self) -> Result<(), DownloadError> {
-        let downloader = Downloader::default();
-        for link in &self.args.links {
-            match PlatformUrl::parse(link) {
-                Ok(PlatformUrl::DirectUrl(url)) => {
-                    info!("Downloading using direct link: {}", url);
-
-                    let options = DownloadOptions {
-                        url: link.clone(),
-                        output_path: self.args.output.clone(),
-                        progress_callback: Some(self.progress_callback.clone()),
-                        extract_archive: self.args.extract,
-                        extract_dir: self.args.extract_dir.clone(),
-                        file_mode: get_file_mode(
-                            self.args.skip_existing,
-                            self.args.force_overwrite,
-                        ),
-                        prompt: None,
-                    };
-                    let _ = downloader
-                        .download(options)
-                        .await
-                        .map_err(|e| error!("{}", e));
-                }
-                Ok(PlatformUrl::Github(project)) => {
-                    info!("Detected GitHub URL, processing as GitHub release");
-                    let handler = ReleaseHandler::<Github>::new();
-                    if let Err(e) = self
-                        .handle_platform_download::<Github, GithubRelease, GithubAsset>(
-                            &handler, &project,
-                        )
-                        .await
-                    {
-                        error!("{}", e);
-                    }
-                }
-                Ok(PlatformUrl::Gitlab(project)) => {
-                    info!("Detected GitLab URL, processing as GitLab release");
-                    let handler = ReleaseHandler::<Gitlab>::new();
-                    if let Err(e) = self
-                        .handle_platform_download::<Gitlab, GitlabRelease, GitlabAsset>(
-                            &handler, &project,
-                        )
-                        .await
-                    {
-                        error!("{}", e);
-                    }
-                }
-                Ok(PlatformUrl::Oci(url)) => {
-                    info!("Downloading using OCI reference: {}", url);
-                    if let Err(e) = self.handle_oci_download(&url).await {
-                        error!("{}", e);
-                    };
-                }
-                Err(err) => error!("Error parsing URL '{}' : {}", link, err),
-            };
+        let options = DownloadOptions {
+            url: url.to_string(),
+            output_path: job_options.output.clone(),
+            progress_callback: Some(progress_callback),
+            extract_archive: self.args.extract,
+            extract_dir: self.args.extract_dir.clone(),
+            file_mode: get_file_mode(self.args.skip_existing, self.args.force_overwrite),
+            prompt: Some(self.overwrite_prompt()),
+            checksum: job_options.checksum.clone(),
+            signature: None,
+            segments: self.args.segments,
+            retry: Some(self.create_retry_policy()),
+            dry_run: self.args.dry_run,
+            headers: None,
+        };
+        if options.dry_run {
+            let plan = Downloader::default().plan(&options).await?;
+            report_plan(target, &plan);
+            return Ok(vec![Downloaded {
+                path: plan.path,
+                checksum: job_options.checksum.as_ref().map(ToString::to_string),
+            }]);
         }
-        Ok(())
+        let path = Downloader::default().download(options).await?;
+        Ok(vec![Downloaded {
+            path,
+            checksum: job_options.checksum.as_ref().map(ToString::to_string),
+        }])
     }
 
-    fn select_asset<A>(&self, assets: &[A]) -> Result<A, DownloadError>
+    async fn select_asset<A>(&self, assets: &[A]) -> Result<A, DownloadError>
     where
         A: Clone,
         A: ReleaseAsset,
@@ -256,26 +897,226 @@ impl DownloadManager {
             return Ok(assets[0].clone());
         }
 
-        info!("\nAvailable assets:");
-        for (i, asset) in assets.iter().enumerate() {
-            let size = asset
-                .size()
-                .map(|s| format!(" ({})", HumanBytes(s)))
-                .unwrap_or_default();
-            info!("{}. {}{}", i + 1, asset.name(), size);
+        // assets are ranked best first
+        if let Some(target) = self.asset_target() {
+            info!(
+                "Selected {} for {} out of {} matching assets",
+                assets[0].name(),
+                target,
+                assets.len()
+            );
+            return Ok(assets[0].clone());
         }
 
-        loop {
-            print!("\nSelect an asset (1-{}): ", assets.len());
-            std::io::Write::flush(&mut std::io::stdout())?;
+        // only one job can prompt at a time
+        let _guard = self.prompt_lock.lock().await;
 
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input)?;
+        let choices = assets
+            .iter()
+            .map(|asset| {
+                let size = asset
+                    .size()
+                    .map(|s| format!(" ({})", HumanBytes(s)))
+                    .unwrap_or_default();
+                format!("{}{}", asset.name(), size)
+            })
+            .collect::<Vec<_>>();
+        // reading stdin blocks, keep it off the runtime so other jobs keep going
+        let index = tokio::task::spawn_blocking(move || prompt_choice(&choices))
+            .await
+            .map_err(std::io::Error::other)??;
+
+        Ok(assets[index].clone())
+    }
+
+    /// Overwrite prompt for the library, taking turns with the other jobs like
+    /// [`Self::select_asset`].
+    fn overwrite_prompt(&self) -> PromptCallback {
+        let prompt_lock = self.prompt_lock.clone();
+        Arc::new(move |file_name| {
+            // nobody is there to answer in JSON mode
+            if events::is_json() {
+                return Err(DownloadError::IoError(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!(
+                        "{} exists, pass --skip-existing or --force-overwrite",
+                        file_name
+                    ),
+                )));
+            }
+            let prompt_lock = prompt_lock.clone();
+            let file_name = file_name.to_string();
+            // the library calls prompts synchronously from inside the runtime
+            tokio::task::block_in_place(|| {
+                Handle::current().block_on(async move {
+                    let _guard = prompt_lock.lock().await;
+                    tokio::task::spawn_blocking(move || confirm_overwrite(&file_name))
+                        .await
+                        .map_err(std::io::Error::other)?
+                })
+            })
+            .map_err(DownloadError::from)
+        })
+    }
+}
+
+/// Asks on stderr whether to overwrite `file_name`.
+fn confirm_overwrite(file_name: &str) -> std::io::Result<bool> {
+    crate::log::suspend(|| {
+        eprint!("Overwrite {}? [y/N] ", file_name);
+        std::io::Write::flush(&mut std::io::stderr())?;
+
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        Ok(matches!(line.trim().to_lowercase().as_str(), "y" | "yes"))
+    })
+}
+
+/// Whether some of `jobs` write to stdout or to the same file, with `default` as the
+/// output of jobs that don't set one.
+fn shares_output(jobs: &[Job], default: Option<&str>) -> bool {
+    let mut files = HashSet::new();
+    jobs.iter()
+        .filter_map(|job| job.options.output.as_deref().or(default))
+        .filter(|output| !output.ends_with('/') && !Path::new(output).is_dir())
+        .any(|output| output == "-" || !files.insert(output))
+}
+
+fn print_summary(results: &[JobResult]) {
+    let width = results
+        .iter()
+        .map(|r| r.target.len())
+        .max()
+        .unwrap_or_default()
+        .max("TARGET".len());
 
-            match input.trim().parse::<usize>() {
-                Ok(n) if n > 0 && n <= assets.len() => return Ok(assets[n - 1].clone()),
-                _ => error!("Invalid selection, please try again."),
+    info!("\n{:<7} {:<width$} RESULT", "STATUS", "TARGET");
+    for r in results {
+        let (status, detail) = match r.result {
+            Ok(ref downloaded) => (
+                "ok",
+                downloaded
+                    .iter()
+                    .map(|file| file.path.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            Err(ref err) => ("failed", err.to_string()),
+        };
+        info!("{:<7} {:<width$} {}", status, r.target, detail);
+    }
+
+    let failed = results.iter().filter(|r| r.result.is_err()).count();
+    info!("\n{} succeeded, {} failed", results.len() - failed, failed);
+}
+
+fn report_plan(target: &str, plan: &DownloadPlan) {
+    let size = plan
+        .size
+        .map(|s| format!(" ({})", HumanBytes(s)))
+        .unwrap_or_default();
+    info!("[{}] {}{} <- {}", plan.action, plan.path, size, plan.url);
+    events::emit(&Event::Plan {
+        target,
+        url: &plan.url,
+        path: &plan.path,
+        size: plan.size,
+        action: &plan.action.to_string(),
+    });
+}
+
+async fn emit_complete(target: &str, file: &Downloaded, duration_ms: u128) {
+    if !events::is_json() {
+        return;
+    }
+
+    let size = tokio::fs::metadata(&file.path)
+        .await
+        .ok()
+        .filter(|meta| meta.is_file())
+        .map(|meta| meta.len());
+
+    // report the digest of what was written even if nothing was verified
+    let checksum = match file.checksum {
+        Some(ref checksum) => Some(checksum.clone()),
+        None if size.is_some() => {
+            let mut hasher = ChecksumHasher::Sha256(Default::default());
+            match hasher.update_from_file(&file.path).await {
+                Ok(()) => Some(format!("sha256:{}", hasher.finalize())),
+                Err(_) => None,
             }
         }
+        None => None,
+    };
+
+    events::emit(&Event::Complete {
+        target,
+        path: &file.path,
+        size,
+        checksum: checksum.as_deref(),
+        duration_ms,
+    });
+}
+
+/// Asks which of `choices` to use, returning its index.
+fn prompt_choice(choices: &[String]) -> std::io::Result<usize> {
+    info!("\nAvailable assets:");
+    for (i, choice) in choices.iter().enumerate() {
+        info!("{}. {}", i + 1, choice);
+    }
+
+    loop {
+        let input = crate::log::suspend(|| {
+            eprint!("\nSelect an asset (1-{}): ", choices.len());
+            std::io::Write::flush(&mut std::io::stderr())?;
+
+            let mut input = String::new();
+            // stdin may be closed or used by `--input -`
+            if std::io::stdin().read_line(&mut input)? == 0 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+            Ok::<_, std::io::Error>(input)
+        })?;
+
+        match input.trim().parse::<usize>() {
+            Ok(n) if n > 0 && n <= choices.len() => return Ok(n - 1),
+            _ => error!("Invalid selection, please try again."),
+        }
+    }
+}
+
+fn short_sha(sha: &str) -> &str {
+    sha.get(..7).unwrap_or(sha)
+}
+
+fn split_tag(project: &str) -> (&str, Option<&str>) {
+    match project.trim().split_once('@') {
+        Some((project, tag)) if !tag.trim().is_empty() => (project, Some(tag.trim())),
+        _ => (project.trim_end_matches('@'), None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(output: Option<&str>) -> Job {
+        Job {
+            target: Target::Direct("https://example.com/file".to_string()),
+            options: JobOptions {
+                output: output.map(String::from),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn shared_output() {
+        assert!(!shares_output(&[job(None), job(None)], None));
+        assert!(!shares_output(&[job(None), job(None)], Some("bin/")));
+        assert!(!shares_output(&[job(Some("a")), job(Some("b"))], None));
+        assert!(shares_output(&[job(None), job(None)], Some("file")));
+        assert!(shares_output(&[job(Some("a")), job(Some("a"))], None));
+        assert!(shares_output(&[job(Some("-")), job(None)], None));
     }
 }
@@ -0,0 +1,150 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+use soar_dl::error::{DownloadError, PlatformError};
+
+static JSON_MODE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    Start {
+        target: &'a str,
+    },
+    Release {
+        target: &'a str,
+        name: &'a str,
+        tag: &'a str,
+    },
+    Asset {
+        target: &'a str,
+        name: &'a str,
+        url: &'a str,
+        size: Option<u64>,
+    },
+    Plan {
+        target: &'a str,
+        url: &'a str,
+        path: &'a str,
+        size: Option<u64>,
+        action: &'a str,
+    },
+    Complete {
+        target: &'a str,
+        path: &'a str,
+        size: Option<u64>,
+        checksum: Option<&'a str>,
+        duration_ms: u128,
+    },
+    Error {
+        target: &'a str,
+        error: ErrorInfo,
+    },
+    Summary {
+        succeeded: usize,
+        failed: usize,
+    },
+    /// A release shown by `list`, with the assets matching the filters
+    ListedRelease {
+        target: &'a str,
+        name: &'a str,
+        tag: &'a str,
+        published_at: &'a str,
+        prerelease: bool,
+        assets: Vec<ListedAsset<'a>>,
+    },
+    /// A workflow run shown by `list`, with the artifacts matching the filters
+    ListedRun {
+        target: &'a str,
+        run_number: u64,
+        name: Option<&'a str>,
+        branch: Option<&'a str>,
+        sha: &'a str,
+        created_at: &'a str,
+        artifacts: Vec<ListedAsset<'a>>,
+    },
+}
+
+#[derive(Serialize)]
+pub struct ListedAsset<'a> {
+    pub name: &'a str,
+    pub url: &'a str,
+    pub size: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct ErrorInfo {
+    pub kind: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available_assets: Option<Vec<String>>,
+}
+
+pub fn init(json: bool) {
+    JSON_MODE.store(json, Ordering::SeqCst);
+}
+
+pub fn is_json() -> bool {
+    JSON_MODE.load(Ordering::SeqCst)
+}
+
+/// Writes `event` to stdout as a single line of JSON, if JSON output is enabled.
+pub fn emit(event: &Event) {
+    if !is_json() {
+        return;
+    }
+    if let Ok(line) = serde_json::to_string(event) {
+        crate::log::suspend(|| println!("{}", line));
+    }
+}
+
+impl From<&PlatformError> for ErrorInfo {
+    fn from(err: &PlatformError) -> Self {
+        let (kind, status, available_assets) = match err {
+            PlatformError::ApiError { status } => ("api_error", Some(status.as_u16()), None),
+            PlatformError::DownloadError(err) => {
+                let (kind, status) = download_error_kind(err);
+                (kind, status, None)
+            }
+            PlatformError::InvalidInput(_) => ("invalid_input", None, None),
+            PlatformError::InvalidResponse => ("invalid_response", None, None),
+            PlatformError::ChecksumNotFound { .. } => ("checksum_not_found", None, None),
+            PlatformError::NoMatchingAssets { available_assets } => {
+                ("no_matching_assets", None, Some(available_assets.clone()))
+            }
+            PlatformError::NoRelease { .. } => ("no_release", None, None),
+            PlatformError::NoWorkflowRun { .. } => ("no_workflow_run", None, None),
+            PlatformError::RepositoryNotFound { .. } => ("repository_not_found", None, None),
+            PlatformError::SignatureNotFound { .. } => ("signature_not_found", None, None),
+        };
+
+        Self {
+            kind,
+            message: err.to_string(),
+            status,
+            available_assets,
+        }
+    }
+}
+
+fn download_error_kind(err: &DownloadError) -> (&'static str, Option<u16>) {
+    match err {
+        DownloadError::InvalidUrl { .. } => ("invalid_url", None),
+        DownloadError::IoError(_) => ("io_error", None),
+        DownloadError::NetworkError { .. } => ("network_error", None),
+        DownloadError::ResourceError { status, .. } => ("resource_error", Some(status.as_u16())),
+        DownloadError::InvalidResponse => ("invalid_response", None),
+        DownloadError::LayersNotFound => ("layers_not_found", None),
+        DownloadError::ChunkError => ("chunk_error", None),
+        DownloadError::FileNameNotFound => ("file_name_not_found", None),
+        DownloadError::ArchiveError(_) => ("archive_error", None),
+        DownloadError::ChecksumMismatch { .. } => ("checksum_mismatch", None),
+        DownloadError::SizeMismatch { .. } => ("size_mismatch", None),
+        DownloadError::SignatureError(_) => ("signature_error", None),
+        DownloadError::NoMatchingPlatform { .. } => ("no_matching_platform", None),
+        DownloadError::TaskError(_) => ("task_error", None),
+        DownloadError::LayersFailed(_) => ("layers_failed", None),
+    }
+}
@@ -0,0 +1,127 @@
+use soar_dl::error::{DownloadError, PlatformError};
+
+use crate::download_manager::JobResult;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitStatus {
+    Success = 0,
+    Failure = 1,
+    /// Same code clap uses for usage errors
+    InvalidInput = 2,
+    PartialFailure = 3,
+    NoMatch = 4,
+}
+
+impl ExitStatus {
+    pub fn from_error(err: &PlatformError) -> Self {
+        match err {
+            PlatformError::InvalidInput(_)
+            | PlatformError::DownloadError(DownloadError::InvalidUrl { .. }) => {
+                ExitStatus::InvalidInput
+            }
+            PlatformError::NoMatchingAssets { .. }
+            | PlatformError::NoRelease { .. }
+            | PlatformError::NoWorkflowRun { .. }
+            | PlatformError::DownloadError(
+                DownloadError::LayersNotFound | DownloadError::NoMatchingPlatform { .. },
+            ) => ExitStatus::NoMatch,
+            _ => ExitStatus::Failure,
+        }
+    }
+
+    /// Partial failure if some targets succeeded. If all failed, the shared cause of
+    /// the failures, or a generic failure if they differ. Invalid input if there were no
+    /// targets at all, e.g. an empty input file.
+    pub fn from_results(results: &[JobResult]) -> Self {
+        if results.is_empty() {
+            return ExitStatus::InvalidInput;
+        }
+
+        let mut errors = results.iter().filter_map(|r| r.result.as_ref().err());
+        let Some(first) = errors.next() else {
+            return ExitStatus::Success;
+        };
+        if results.iter().any(|r| r.result.is_ok()) {
+            return ExitStatus::PartialFailure;
+        }
+
+        let status = ExitStatus::from_error(first);
+        if errors.all(|err| ExitStatus::from_error(err) == status) {
+            status
+        } else {
+            ExitStatus::Failure
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download_manager::Downloaded;
+
+    fn ok() -> JobResult {
+        JobResult {
+            target: "ok".to_string(),
+            result: Ok(vec![Downloaded {
+                path: "file".to_string(),
+                checksum: None,
+            }]),
+        }
+    }
+
+    fn failed(err: PlatformError) -> JobResult {
+        JobResult {
+            target: "failed".to_string(),
+            result: Err(err),
+        }
+    }
+
+    fn no_release() -> PlatformError {
+        PlatformError::NoRelease { tag: None }
+    }
+
+    #[test]
+    fn nothing_attempted() {
+        assert_eq!(ExitStatus::from_results(&[]), ExitStatus::InvalidInput);
+    }
+
+    #[test]
+    fn all_succeeded() {
+        assert_eq!(ExitStatus::from_results(&[ok(), ok()]), ExitStatus::Success);
+    }
+
+    #[test]
+    fn some_failed() {
+        assert_eq!(
+            ExitStatus::from_results(&[ok(), failed(no_release())]),
+            ExitStatus::PartialFailure
+        );
+    }
+
+    #[test]
+    fn all_failed_alike() {
+        assert_eq!(
+            ExitStatus::from_results(&[failed(no_release()), failed(no_release())]),
+            ExitStatus::NoMatch
+        );
+        assert_eq!(
+            ExitStatus::from_results(&[failed(PlatformError::InvalidInput("x".to_string()))]),
+            ExitStatus::InvalidInput
+        );
+    }
+
+    #[test]
+    fn all_failed_differently() {
+        assert_eq!(
+            ExitStatus::from_results(&[
+                failed(no_release()),
+                failed(PlatformError::InvalidInput("x".to_string())),
+            ]),
+            ExitStatus::Failure
+        );
+        assert_eq!(
+            ExitStatus::from_results(&[failed(PlatformError::InvalidResponse)]),
+            ExitStatus::Failure
+        );
+    }
+}
@@ -1,20 +1,35 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    OnceLock,
+};
+
+use indicatif::MultiProgress;
 
 static QUIET_MODE: AtomicBool = AtomicBool::new(false);
+static PROGRESS: OnceLock<MultiProgress> = OnceLock::new();
 
-pub fn init(quiet: bool) {
+pub fn init(quiet: bool, progress: MultiProgress) {
     QUIET_MODE.store(quiet, Ordering::SeqCst);
+    let _ = PROGRESS.set(progress);
 }
 
 pub fn is_quiet() -> bool {
     QUIET_MODE.load(Ordering::SeqCst)
 }
 
+/// Hides the progress bars while `f` writes to the terminal.
+pub fn suspend<F: FnOnce() -> R, R>(f: F) -> R {
+    match PROGRESS.get() {
+        Some(progress) => progress.suspend(f),
+        None => f(),
+    }
+}
+
 #[macro_export]
 macro_rules! info {
     ($($arg:tt)*) => {
         if !$crate::log::is_quiet() {
-            println!("{}", format!($($arg)*));
+            $crate::log::suspend(|| eprintln!("{}", format!($($arg)*)));
         }
     };
 }
@@ -23,7 +38,7 @@ macro_rules! info {
 macro_rules! error {
     ($($arg:tt)*) => {
         if !$crate::log::is_quiet() {
-            eprintln!("{}", format!($($arg)*));
+            $crate::log::suspend(|| eprintln!("{}", format!($($arg)*)));
         }
     };
 }
@@ -1,24 +1,30 @@
 use std::{error::Error, sync::Arc};
 
 use clap::Parser;
-use cli::Args;
+use cli::{Args, Command};
 use download_manager::DownloadManager;
-use progress::create_progress_bar;
+use exit::ExitStatus;
+use indicatif::MultiProgress;
 use soar_dl::http_client::{configure_http_client, create_http_header_map};
 
 mod cli;
 mod download_manager;
+mod events;
+mod exit;
 mod log;
+mod manifest;
 mod progress;
 
 #[tokio::main]
 async fn main() {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    if let Err(err) = args.validate() {
+        err.exit();
+    }
 
-    log::init(args.quiet);
-
-    let progress_bar = create_progress_bar();
-    let progress_callback = Arc::new(move |state| progress::handle_progress(state, &progress_bar));
+    let progress = MultiProgress::new();
+    log::init(args.quiet, progress.clone());
+    events::init(args.json);
 
     let proxy = args.proxy.clone();
     let user_agent = args.user_agent.clone();
@@ -39,9 +45,14 @@ async fn main() {
         if let Some(source) = err.source() {
             error!("  Caused by: {}", source);
         }
-        std::process::exit(1);
+        std::process::exit(ExitStatus::Failure as i32);
     };
 
-    let manager = DownloadManager::new(args, progress_callback);
-    manager.execute().await;
+    let command = args.command.take();
+    let manager = Arc::new(DownloadManager::new(args, progress));
+    let results = match command {
+        Some(Command::List { targets }) => manager.list(&targets).await,
+        None => manager.execute().await,
+    };
+    std::process::exit(ExitStatus::from_results(&results) as i32);
 }
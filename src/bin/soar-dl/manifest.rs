@@ -0,0 +1,282 @@
+use std::io::Read;
+
+use regex::Regex;
+use serde::Deserialize;
+use soar_dl::{checksum::Checksum, platform::ForgeHost};
+
+use crate::download_manager::{Job, JobOptions, Target};
+
+#[derive(Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    download: Vec<Entry>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    target: String,
+    output: Option<String>,
+    regex: Option<OneOrMany>,
+    glob: Option<OneOrMany>,
+    #[serde(rename = "match")]
+    match_keywords: Option<OneOrMany>,
+    exclude: Option<OneOrMany>,
+    checksum: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Reads download targets from `path`, or stdin if `path` is `-`.
+///
+/// Plain text files have one target per line followed by optional `key=value`
+/// options (`output`, `regex`, `glob`, `match`, `exclude`, `checksum`). TOML and JSON
+/// files list the same fields in `download` entries.
+pub fn load(path: &str, hosts: &[ForgeHost]) -> Result<Vec<Job>, String> {
+    let content = if path == "-" {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|err| err.to_string())?;
+        content
+    } else {
+        std::fs::read_to_string(path).map_err(|err| err.to_string())?
+    };
+
+    parse(&content, path, hosts)
+}
+
+/// Parses the contents of the file at `path`, by its extension or else its contents.
+fn parse(content: &str, path: &str, hosts: &[ForgeHost]) -> Result<Vec<Job>, String> {
+    let trimmed = content.trim_start();
+    let entries = if path.ends_with(".toml") || trimmed.starts_with("[[") {
+        toml::from_str::<Manifest>(content)
+            .map_err(|err| err.to_string())?
+            .download
+    } else if path.ends_with(".json") || trimmed.starts_with(['{', '[']) {
+        serde_json::from_str::<Vec<Entry>>(content)
+            .or_else(|_| serde_json::from_str::<Manifest>(content).map(|m| m.download))
+            .map_err(|err| err.to_string())?
+    } else {
+        return parse_lines(content, hosts);
+    };
+
+    entries
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            entry
+                .into_job(hosts)
+                .map_err(|err| format!("entry {}: {}", i + 1, err))
+        })
+        .collect()
+}
+
+fn parse_lines(content: &str, hosts: &[ForgeHost]) -> Result<Vec<Job>, String> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(lineno, line)| {
+            parse_line(line, hosts).map_err(|err| format!("line {}: {}", lineno, err))
+        })
+        .collect()
+}
+
+fn parse_line(line: &str, hosts: &[ForgeHost]) -> Result<Job, String> {
+    let mut tokens = line.split_whitespace();
+    let mut entry = Entry {
+        target: tokens.next().unwrap_or_default().to_string(),
+        ..Default::default()
+    };
+
+    for token in tokens {
+        let Some((key, value)) = token.split_once('=') else {
+            return Err(format!("expected key=value, got '{}'", token));
+        };
+        let value = value.to_string();
+        match key {
+            "output" => entry.output = Some(value),
+            "regex" => push(&mut entry.regex, value),
+            "glob" => push(&mut entry.glob, value),
+            "match" => push(&mut entry.match_keywords, value),
+            "exclude" => push(&mut entry.exclude, value),
+            "checksum" => entry.checksum = Some(value),
+            _ => return Err(format!("unknown option '{}'", key)),
+        }
+    }
+
+    entry.into_job(hosts)
+}
+
+fn push(values: &mut Option<OneOrMany>, value: String) {
+    let mut list = values.take().map(OneOrMany::into_vec).unwrap_or_default();
+    list.push(value);
+    *values = Some(OneOrMany::Many(list));
+}
+
+impl OneOrMany {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+impl Entry {
+    fn into_job(self, hosts: &[ForgeHost]) -> Result<Job, String> {
+        let target = Target::parse(&self.target, hosts).map_err(|err| err.to_string())?;
+
+        let regexes = self.regex.map(OneOrMany::into_vec);
+        for pattern in regexes.iter().flatten() {
+            Regex::new(pattern).map_err(|_| format!("invalid regex '{}'", pattern))?;
+        }
+
+        let checksum = self
+            .checksum
+            .map(|value| {
+                Checksum::parse(&value).ok_or_else(|| format!("invalid checksum '{}'", value))
+            })
+            .transpose()?;
+
+        Ok(Job {
+            target,
+            options: JobOptions {
+                output: self.output,
+                regexes,
+                globs: self.glob.map(OneOrMany::into_vec),
+                match_keywords: self.match_keywords.map(OneOrMany::into_vec),
+                exclude_keywords: self.exclude.map(OneOrMany::into_vec),
+                checksum,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_with_options() {
+        let job = parse_line(
+            "github:pkgforge/soar@nightly glob=*x86_64-linux glob=*.tar.gz output=bin/",
+            &[],
+        )
+        .unwrap();
+        assert!(
+            matches!(job.target, Target::Github(ref project) if project == "pkgforge/soar@nightly")
+        );
+        assert_eq!(job.options.output.as_deref(), Some("bin/"));
+        assert_eq!(
+            job.options.globs,
+            Some(vec!["*x86_64-linux".to_string(), "*.tar.gz".to_string()])
+        );
+        assert_eq!(job.options.regexes, None);
+    }
+
+    #[test]
+    fn line_with_checksum() {
+        let digest = "0".repeat(64);
+        let job = parse_line(
+            &format!("https://example.com/file.tar.gz checksum=sha256:{}", digest),
+            &[],
+        )
+        .unwrap();
+        assert!(matches!(job.target, Target::Direct(_)));
+        assert_eq!(job.options.checksum, Some(Checksum::Sha256(digest)));
+    }
+
+    #[test]
+    fn line_errors() {
+        assert!(parse_line("github:o/r output", &[])
+            .unwrap_err()
+            .contains("key=value"));
+        assert!(parse_line("github:o/r size=1", &[])
+            .unwrap_err()
+            .contains("unknown option"));
+        assert!(parse_line("github:o/r regex=(", &[])
+            .unwrap_err()
+            .contains("invalid regex"));
+        assert!(parse_line("github:o/r checksum=md5:00", &[])
+            .unwrap_err()
+            .contains("invalid checksum"));
+    }
+
+    #[test]
+    fn lines_skip_comments() {
+        let jobs = parse(
+            "# targets\n\ngitlab:inkscape/inkscape match=AppImage\n  \noci:alpine:3.20\n",
+            "downloads.txt",
+            &[],
+        )
+        .unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert!(matches!(jobs[0].target, Target::Gitlab(_)));
+        assert_eq!(
+            jobs[0].options.match_keywords,
+            Some(vec!["AppImage".to_string()])
+        );
+
+        let err = parse("github:o/r\ngithub:o/r bad\n", "-", &[]).unwrap_err();
+        assert!(err.starts_with("line 2:"), "{}", err);
+    }
+
+    #[test]
+    fn toml_manifest() {
+        let jobs = parse(
+            r#"
+[[download]]
+target = "github:pkgforge/soar@nightly"
+glob = ["*x86_64-linux"]
+output = "bin/"
+
+[[download]]
+target = "gitlab:inkscape/inkscape"
+match = "AppImage"
+"#,
+            "downloads.toml",
+            &[],
+        )
+        .unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(
+            jobs[0].options.globs,
+            Some(vec!["*x86_64-linux".to_string()])
+        );
+        assert_eq!(
+            jobs[1].options.match_keywords,
+            Some(vec!["AppImage".to_string()])
+        );
+
+        let err = parse("[[download]]\ntarget = \"o/r\"\nsize = 1\n", "-", &[]).unwrap_err();
+        assert!(err.contains("size"), "{}", err);
+    }
+
+    #[test]
+    fn json_manifest() {
+        let jobs = parse(
+            r#"[{"target": "github:o/r", "exclude": ["b3sum", "sig"]}]"#,
+            "downloads.json",
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            jobs[0].options.exclude_keywords,
+            Some(vec!["b3sum".to_string(), "sig".to_string()])
+        );
+
+        let jobs = parse(r#"{"download": [{"target": "github:o/r"}]}"#, "-", &[]).unwrap();
+        assert_eq!(jobs.len(), 1);
+
+        let err = parse(r#"[{"target": "github:o/r", "regex": "("}]"#, "-", &[]).unwrap_err();
+        assert!(err.starts_with("entry 1:"), "{}", err);
+    }
+}
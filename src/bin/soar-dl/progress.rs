@@ -4,7 +4,7 @@ use soar_dl::downloader::DownloadState;
 pub fn create_progress_bar() -> ProgressBar {
     let progress_bar = ProgressBar::new(0);
     let style = ProgressStyle::with_template(
-        "[{wide_bar:.green/white}] {bytes_per_sec:14} {computed_bytes:22}",
+        "{prefix} [{wide_bar:.green/white}] {bytes_per_sec:14} {computed_bytes:22}",
     )
     .unwrap()
     .with_key("computed_bytes", format_bytes)
@@ -32,6 +32,7 @@ pub fn handle_progress(state: DownloadState, progress_bar: &ProgressBar) {
             progress_bar.set_position(progress);
         }
         DownloadState::Complete => progress_bar.finish(),
+        DownloadState::Error => progress_bar.abandon(),
         _ => {}
     }
 }
@@ -0,0 +1,258 @@
+use std::{fmt::Display, path::Path};
+
+use sha2::{Digest, Sha256, Sha512};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, BufReader},
+};
+
+use crate::error::DownloadError;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Checksum {
+    Sha256(String),
+    Sha512(String),
+    Blake3(String),
+}
+
+pub enum ChecksumHasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Checksum {
+    /// Parses `<algo>:<hex>` (e.g. `sha256:...`, `blake3:...`). A bare hex digest is
+    /// treated as sha256 or sha512 based on its length.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        match value.split_once(':') {
+            Some((algo, digest)) => Self::from_parts(Some(algo), digest),
+            None => Self::from_parts(None, value),
+        }
+    }
+
+    pub fn from_parts(algo: Option<&str>, digest: &str) -> Option<Self> {
+        let digest = digest.trim();
+        if digest.is_empty() || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digest = digest.to_lowercase();
+        let algo = algo.map(|a| a.trim().to_lowercase().replace('-', ""));
+
+        match (algo.as_deref(), digest.len()) {
+            (Some("sha256") | None, 64) => Some(Self::Sha256(digest)),
+            (Some("sha512") | None, 128) => Some(Self::Sha512(digest)),
+            (Some("blake3" | "b3"), 64) => Some(Self::Blake3(digest)),
+            _ => None,
+        }
+    }
+
+    /// Finds the checksum of `file_name` in the contents of a checksum file.
+    ///
+    /// Supports coreutils (`<hex>  <name>`), BSD-style (`SHA256 (<name>) = <hex>`) and
+    /// files that contain only a single hash.
+    pub fn from_checksum_file(content: &str, checksum_file: &str, file_name: &str) -> Option<Self> {
+        let algo_hint = algorithm_hint(checksum_file);
+        let lines: Vec<&str> = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+
+        for line in &lines {
+            if let Some((lhs, digest)) = line.rsplit_once(" = ") {
+                if let Some((algo, name)) = lhs.split_once(" (") {
+                    let name = name.trim_end_matches(')');
+                    if is_same_file(name, file_name) {
+                        return Self::from_parts(Some(algo), digest);
+                    }
+                }
+                continue;
+            }
+
+            match line.split_once(char::is_whitespace) {
+                Some((digest, name)) => {
+                    let name = name.trim().trim_start_matches(['*', ' ']);
+                    if is_same_file(name, file_name) {
+                        return Self::from_parts(algo_hint, digest);
+                    }
+                }
+                None if lines.len() == 1 => return Self::from_parts(algo_hint, line),
+                None => {}
+            }
+        }
+
+        None
+    }
+
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            Checksum::Sha256(_) => "sha256",
+            Checksum::Sha512(_) => "sha512",
+            Checksum::Blake3(_) => "blake3",
+        }
+    }
+
+    pub fn digest(&self) -> &str {
+        match self {
+            Checksum::Sha256(digest) | Checksum::Sha512(digest) | Checksum::Blake3(digest) => {
+                digest
+            }
+        }
+    }
+
+    pub fn hasher(&self) -> ChecksumHasher {
+        match self {
+            Checksum::Sha256(_) => ChecksumHasher::Sha256(Sha256::new()),
+            Checksum::Sha512(_) => ChecksumHasher::Sha512(Sha512::new()),
+            Checksum::Blake3(_) => ChecksumHasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    pub fn verify(&self, actual: &str) -> Result<(), DownloadError> {
+        if self.digest().eq_ignore_ascii_case(actual) {
+            Ok(())
+        } else {
+            Err(DownloadError::ChecksumMismatch {
+                expected: self.to_string(),
+                actual: format!("{}:{}", self.algorithm(), actual),
+            })
+        }
+    }
+}
+
+fn algorithm_hint(checksum_file: &str) -> Option<&'static str> {
+    let name = checksum_file.to_lowercase();
+    if name.contains("sha512") {
+        Some("sha512")
+    } else if name.contains("blake3") || name.contains("b3sum") || name.ends_with(".b3") {
+        Some("blake3")
+    } else if name.contains("sha256") {
+        Some("sha256")
+    } else {
+        None
+    }
+}
+
+fn is_same_file(listed: &str, file_name: &str) -> bool {
+    let listed = listed.trim();
+    listed == file_name || listed.rsplit('/').next() == Some(file_name)
+}
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.algorithm(), self.digest())
+    }
+}
+
+impl ChecksumHasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            ChecksumHasher::Sha256(hasher) => hasher.update(data),
+            ChecksumHasher::Sha512(hasher) => hasher.update(data),
+            ChecksumHasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    pub async fn update_from_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), DownloadError> {
+        let mut file = BufReader::new(File::open(path).await?);
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            self.update(&buf[..n]);
+        }
+        Ok(())
+    }
+
+    pub fn finalize(self) -> String {
+        match self {
+            ChecksumHasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            ChecksumHasher::Sha512(hasher) => format!("{:x}", hasher.finalize()),
+            ChecksumHasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA256: &str = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+
+    #[test]
+    fn parse_prefixed() {
+        assert_eq!(
+            Checksum::parse(&format!("sha256:{}", SHA256)),
+            Some(Checksum::Sha256(SHA256.to_string()))
+        );
+        assert_eq!(
+            Checksum::parse(&format!("SHA-256:{}", SHA256.to_uppercase())),
+            Some(Checksum::Sha256(SHA256.to_string()))
+        );
+        assert_eq!(
+            Checksum::parse(&format!("b3:{}", SHA256)),
+            Some(Checksum::Blake3(SHA256.to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_bare_by_length() {
+        assert_eq!(
+            Checksum::parse(SHA256),
+            Some(Checksum::Sha256(SHA256.to_string()))
+        );
+        let sha512 = SHA256.repeat(2);
+        assert_eq!(Checksum::parse(&sha512), Some(Checksum::Sha512(sha512)));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(Checksum::parse(""), None);
+        assert_eq!(Checksum::parse("sha256:xyz"), None);
+        assert_eq!(Checksum::parse(&format!("sha512:{}", SHA256)), None);
+        assert_eq!(Checksum::parse(&format!("md5:{}", SHA256)), None);
+    }
+
+    #[test]
+    fn checksum_file_coreutils() {
+        let content = format!(
+            "# comment\n{}  other\n{} *dist/tool-x86_64-linux\n",
+            "0".repeat(64),
+            SHA256
+        );
+        assert_eq!(
+            Checksum::from_checksum_file(&content, "SHA256SUMS", "tool-x86_64-linux"),
+            Some(Checksum::Sha256(SHA256.to_string()))
+        );
+        assert_eq!(
+            Checksum::from_checksum_file(&content, "SHA256SUMS", "missing"),
+            None
+        );
+    }
+
+    #[test]
+    fn checksum_file_bsd() {
+        let content = format!("SHA256 (tool) = {}\n", SHA256);
+        assert_eq!(
+            Checksum::from_checksum_file(&content, "checksums.txt", "tool"),
+            Some(Checksum::Sha256(SHA256.to_string()))
+        );
+    }
+
+    #[test]
+    fn checksum_file_single_hash() {
+        assert_eq!(
+            Checksum::from_checksum_file(SHA256, "tool.b3", "tool"),
+            Some(Checksum::Blake3(SHA256.to_string()))
+        );
+        // a lone hash only applies when it's the only line
+        let content = format!("{}\n{}  other\n", SHA256, SHA256);
+        assert_eq!(Checksum::from_checksum_file(&content, "SUMS", "tool"), None);
+    }
+}
@@ -1,32 +1,45 @@
 use std::{
     collections::{HashMap, HashSet},
+    fmt::Display,
     fs::Permissions,
+    io::SeekFrom,
     os::unix::fs::PermissionsExt,
-    path::PathBuf,
-    sync::{Arc, Mutex},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
 };
 
-use compak::Archive;
-use futures::{future::join_all, TryStreamExt};
+use futures::{future::try_join_all, TryStreamExt};
 use regex::Regex;
-use reqwest::header::{HeaderMap, CONTENT_DISPOSITION, ETAG, LAST_MODIFIED};
+use reqwest::{
+    header::{
+        HeaderMap, HeaderValue, ACCEPT_RANGES, CONTENT_DISPOSITION, CONTENT_LENGTH, ETAG, IF_RANGE,
+        LAST_MODIFIED, RANGE,
+    },
+    StatusCode,
+};
 
 use tokio::{
     fs::{self, OpenOptions},
-    io::AsyncWriteExt,
-    sync::Semaphore,
-    task,
+    io::{AsyncSeekExt, AsyncWriteExt},
+    time::sleep,
 };
 use url::Url;
 
 use crate::{
+    checksum::{Checksum, ChecksumHasher},
     error::DownloadError,
     http_client::SHARED_CLIENT,
-    oci::{OciClient, OciLayer, OciManifest, Reference},
-    resume::ResumeSupport,
+    oci::{OciClient, OciLayer, OciManifest, OciPlatform, Reference},
+    resume::{ResumeSupport, SegmentProgress},
+    retry::{retry_after, RetryPolicy},
+    scheduler::Scheduler,
+    signature::DetachedSignature,
     utils::{
-        build_absolute_path, default_prompt_confirm, extract_filename, extract_filename_from_url,
-        is_elf, matches_pattern, FileMode,
+        default_prompt_confirm, extract_archive, extract_filename, extract_filename_from_url,
+        is_elf, matches_pattern, FileMode, PromptCallback,
     },
 };
 
@@ -47,7 +60,67 @@ pub struct DownloadOptions {
     pub extract_archive: bool,
     pub extract_dir: Option<String>,
     pub file_mode: FileMode,
-    pub prompt: Option<Arc<dyn Fn(&str) -> Result<bool, DownloadError> + Send + Sync + 'static>>,
+    pub prompt: Option<PromptCallback>,
+    pub checksum: Option<Checksum>,
+    /// Verified before the file is moved into place, like `checksum`
+    pub signature: Option<DetachedSignature>,
+    /// Number of concurrent range requests, at most 16 and one per MiB
+    pub segments: Option<u64>,
+    pub retry: Option<RetryPolicy>,
+    pub dry_run: bool,
+    /// Extra request headers, e.g. for authentication
+    pub headers: Option<HeaderMap>,
+}
+
+const MIN_SEGMENT_SIZE: u64 = 1024 * 1024;
+const MAX_SEGMENTS: u64 = 16;
+const DEFAULT_OCI_CONCURRENCY: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileAction {
+    Create,
+    Resume,
+    Overwrite,
+    Prompt,
+    Skip,
+    Stdout,
+}
+
+#[derive(Clone, Debug)]
+pub struct DownloadPlan {
+    pub url: String,
+    pub path: String,
+    pub size: Option<u64>,
+    pub action: FileAction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkipReason {
+    /// Downloaded earlier by the same downloader
+    AlreadyDownloaded,
+    /// No `org.opencontainers.image.title` annotation to name the file after
+    Untitled,
+}
+
+#[derive(Debug)]
+pub enum LayerStatus {
+    Downloaded,
+    Skipped(SkipReason),
+    Planned(DownloadPlan),
+    Failed(DownloadError),
+}
+
+#[derive(Debug)]
+pub struct LayerResult {
+    pub title: String,
+    pub digest: String,
+    pub path: PathBuf,
+    pub status: LayerStatus,
+}
+
+#[derive(Debug, Default)]
+pub struct OciDownloadReport {
+    pub layers: Vec<LayerResult>,
 }
 
 pub struct Downloader<'a> {
@@ -67,6 +140,9 @@ pub struct OciDownloadOptions {
     pub exclude_keywords: Vec<String>,
     pub exact_case: bool,
     pub file_mode: FileMode,
+    pub retry: Option<RetryPolicy>,
+    pub platform: Option<OciPlatform>,
+    pub dry_run: bool,
 }
 
 impl<'a> Default for Downloader<'a> {
@@ -83,37 +159,91 @@ impl Downloader<'_> {
     }
 
     pub async fn download(&self, options: DownloadOptions) -> Result<String, DownloadError> {
+        if options.dry_run {
+            return self.plan(&options).await.map(|plan| plan.path);
+        }
+
+        let mut attempt = 1;
+        loop {
+            match self.try_download(&options).await {
+                Err(err) => {
+                    // streamed output can't be rewound, so don't retry it
+                    let delay = options
+                        .retry
+                        .as_ref()
+                        .filter(|_| options.output_path.as_deref() != Some("-"))
+                        .and_then(|policy| policy.next_delay(attempt, &err));
+
+                    let Some(delay) = delay else {
+                        return Err(err);
+                    };
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Resolves where `options.url` would be written and how big it is, using a HEAD
+    /// request. Nothing is written to disk.
+    pub async fn plan(&self, options: &DownloadOptions) -> Result<DownloadPlan, DownloadError> {
         let url = Url::parse(&options.url).map_err(|err| DownloadError::InvalidUrl {
             url: options.url.clone(),
             source: err,
         })?;
 
-        let hash_fallback = || {
-            let mut hasher = blake3::Hasher::new();
-            hasher.update(options.url.as_bytes());
-            let result = hasher.finalize();
-            result.to_hex().to_string()
-        };
+        let response = self
+            .client
+            .head(url)
+            .headers(options.headers.clone().unwrap_or_default())
+            .send()
+            .await
+            .map_err(|err| DownloadError::NetworkError { source: err })?;
 
-        let (provisional_path, final_dir) = if let Some(ref out) = options.output_path {
-            if out.ends_with('/') {
-                let dir = PathBuf::from(out);
-                let base = extract_filename_from_url(&options.url).unwrap_or_else(hash_fallback);
-                (dir.join(&base), Some(dir))
-            } else {
-                let p = PathBuf::from(out);
-                if p.is_dir() {
-                    let base =
-                        extract_filename_from_url(&options.url).unwrap_or_else(hash_fallback);
-                    (p.join(&base), Some(p))
-                } else {
-                    (p, None)
-                }
-            }
-        } else {
-            let base = extract_filename_from_url(&options.url).unwrap_or_else(hash_fallback);
-            (PathBuf::from(&base), None)
-        };
+        let status = response.status();
+        // some servers don't support HEAD, the download itself may still work
+        if !status.is_success() && status != StatusCode::METHOD_NOT_ALLOWED {
+            return Err(DownloadError::ResourceError {
+                url: options.url.clone(),
+                status,
+                retry_after: retry_after(response.headers()),
+            });
+        }
+
+        let size = response
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|len| len.parse().ok());
+
+        if options.output_path.as_deref() == Some("-") {
+            return Ok(DownloadPlan {
+                url: options.url.clone(),
+                path: "-".to_string(),
+                size,
+                action: FileAction::Stdout,
+            });
+        }
+
+        let (provisional_path, final_dir) = output_paths(options);
+        let path = final_target(options, provisional_path, final_dir, response.headers());
+
+        Ok(DownloadPlan {
+            url: options.url.clone(),
+            action: FileAction::resolve(&path, &options.file_mode),
+            path: path.to_string_lossy().into(),
+            size,
+        })
+    }
+
+    async fn try_download(&self, options: &DownloadOptions) -> Result<String, DownloadError> {
+        let url = Url::parse(&options.url).map_err(|err| DownloadError::InvalidUrl {
+            url: options.url.clone(),
+            source: err,
+        })?;
+
+        let (provisional_path, final_dir) = output_paths(options);
 
         if let Some(output_dir) = provisional_path.parent() {
             if !output_dir.exists() {
@@ -123,16 +253,23 @@ impl Downloader<'_> {
 
         let (part_path, meta_path) = ResumeSupport::get_part_paths(&provisional_path);
         let (mut etag, mut last_modified) = ResumeSupport::read_metadata(&meta_path).await?;
+        let mut segments = ResumeSupport::read_segments(&meta_path).await?;
 
         let mut attempt = 0;
         let mut downloaded = if fs::try_exists(&part_path).await? {
             fs::metadata(&part_path).await?.len()
         } else {
+            segments.clear();
             0
         };
+        // the part file of a segmented download is preallocated, its length says nothing
+        // about what's been downloaded
+        if !segments.is_empty() {
+            downloaded = 0;
+        }
 
         loop {
-            let mut headers = HeaderMap::new();
+            let mut headers = options.headers.clone().unwrap_or_default();
 
             ResumeSupport::prepare_resume_headers(&mut headers, downloaded, &etag, &last_modified);
 
@@ -170,6 +307,7 @@ impl Downloader<'_> {
                 etag = remote_etag.clone();
                 last_modified = remote_modified.clone();
                 downloaded = 0;
+                segments.clear();
                 attempt += 1;
                 continue;
             }
@@ -177,48 +315,59 @@ impl Downloader<'_> {
             if !status.is_success() {
                 return Err(DownloadError::ResourceError {
                     status,
-                    url: options.url,
+                    url: options.url.clone(),
+                    retry_after: retry_after(response.headers()),
                 });
             }
 
             if options.output_path.as_deref() == Some("-") {
+                if options.signature.is_some() {
+                    return Err(DownloadError::SignatureError(
+                        "output written to stdout can't be verified".to_string(),
+                    ));
+                }
                 let mut stdout = tokio::io::stdout();
                 let mut stream = response.bytes_stream();
+                let mut hasher = options.checksum.as_ref().map(Checksum::hasher);
 
                 while let Some(chunk) = stream
                     .try_next()
                     .await
                     .map_err(|_| DownloadError::ChunkError)?
                 {
+                    if let Some(ref mut hasher) = hasher {
+                        hasher.update(&chunk);
+                    }
                     stdout.write_all(&chunk).await?;
                     stdout.flush().await?;
                 }
+
+                if let (Some(checksum), Some(hasher)) = (&options.checksum, hasher) {
+                    checksum.verify(&hasher.finalize())?;
+                }
                 return Ok("-".to_string());
             }
 
-            let header_name = response
-                .headers()
-                .get(CONTENT_DISPOSITION)
-                .and_then(|header| header.to_str().ok())
-                .and_then(extract_filename);
-
-            let final_target = match &options.output_path {
-                Some(_) => provisional_path,
-                None => match header_name {
-                    Some(ref name) => final_dir
-                        .as_ref()
-                        .map(|dir| dir.join(name))
-                        .unwrap_or_else(|| PathBuf::from(name)),
-                    None => provisional_path,
-                },
-            };
+            let final_target = final_target(
+                options,
+                provisional_path.clone(),
+                final_dir.clone(),
+                response.headers(),
+            );
 
             if final_target.exists() && !part_path.exists() {
                 match options.file_mode {
-                    FileMode::SkipExisting => return Ok(final_target.to_string_lossy().into()),
-                    FileMode::ForceOverwrite => {
-                        fs::remove_file(&final_target).await.ok();
+                    FileMode::SkipExisting => {
+                        // an existing file is only as good as its checksum
+                        if let Some(ref checksum) = options.checksum {
+                            let mut hasher = checksum.hasher();
+                            hasher.update_from_file(&final_target).await?;
+                            checksum.verify(&hasher.finalize())?;
+                        }
+                        return Ok(final_target.to_string_lossy().into());
                     }
+                    // replaced by the rename once the download is verified
+                    FileMode::ForceOverwrite => {}
                     FileMode::PromptOverwrite => {
                         let target = final_target.to_string_lossy().to_string();
                         let proceed = if let Some(prompt) = &options.prompt {
@@ -241,36 +390,122 @@ impl Downloader<'_> {
                 callback(DownloadState::Preparing(total_size));
             }
 
-            let mut file = if should_truncate || downloaded == 0 {
-                fs::remove_file(&part_path).await.ok();
-                downloaded = 0;
-                OpenOptions::new()
-                    .create(true)
-                    .write(true)
-                    .truncate(true)
-                    .open(&part_path)
-                    .await?
+            // segments left by an earlier attempt are resumed, if the server still serves
+            // ranges of the same file
+            let resumable = response.status() == StatusCode::OK
+                && accepts_ranges(response.headers())
+                && segments
+                    .last()
+                    .is_some_and(|last| last.end + 1 == total_size);
+            if !resumable {
+                let count = segment_count(
+                    response.status(),
+                    response.headers(),
+                    options.segments,
+                    downloaded,
+                    total_size,
+                );
+                segments = if count > 1 {
+                    segment_ranges(total_size, count)
+                } else {
+                    Vec::new()
+                };
+            }
+
+            let digest = if !segments.is_empty() {
+                drop(response);
+                match self
+                    .download_segments(
+                        &url,
+                        &part_path,
+                        &meta_path,
+                        std::mem::take(&mut segments),
+                        (remote_etag, remote_modified),
+                        options,
+                    )
+                    .await
+                {
+                    // the file changed between segments, start over on the new one
+                    Err(DownloadError::ResourceError {
+                        status: StatusCode::OK,
+                        ..
+                    }) if attempt == 0 => {
+                        etag = None;
+                        last_modified = None;
+                        attempt += 1;
+                        continue;
+                    }
+                    result => result?,
+                }
+
+                match options.checksum {
+                    Some(ref checksum) => {
+                        let mut hasher = checksum.hasher();
+                        hasher.update_from_file(&part_path).await?;
+                        Some(hasher.finalize())
+                    }
+                    None => None,
+                }
             } else {
-                OpenOptions::new()
-                    .create(true)
-                    .append(true)
-                    .open(&part_path)
-                    .await?
-            };
+                let mut file = if should_truncate || downloaded == 0 {
+                    fs::remove_file(&part_path).await.ok();
+                    downloaded = 0;
+                    OpenOptions::new()
+                        .create(true)
+                        .write(true)
+                        .truncate(true)
+                        .open(&part_path)
+                        .await?
+                } else {
+                    OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&part_path)
+                        .await?
+                };
 
-            ResumeSupport::write_metadata(&meta_path, remote_etag, remote_modified).await?;
+                ResumeSupport::write_metadata(&meta_path, remote_etag, remote_modified).await?;
 
-            let mut stream = response.bytes_stream();
-            while let Some(chunk) = stream
-                .try_next()
-                .await
-                .map_err(|_| DownloadError::ChunkError)?
-            {
-                file.write_all(&chunk).await?;
-                downloaded += chunk.len() as u64;
+                let mut hasher = options.checksum.as_ref().map(Checksum::hasher);
+                if let Some(ref mut hasher) = hasher {
+                    if downloaded > 0 {
+                        hasher.update_from_file(&part_path).await?;
+                    }
+                }
 
-                if let Some(ref callback) = options.progress_callback {
-                    callback(DownloadState::Progress(downloaded));
+                let mut stream = response.bytes_stream();
+                while let Some(chunk) = stream
+                    .try_next()
+                    .await
+                    .map_err(|_| DownloadError::ChunkError)?
+                {
+                    if let Some(ref mut hasher) = hasher {
+                        hasher.update(&chunk);
+                    }
+                    file.write_all(&chunk).await?;
+                    downloaded += chunk.len() as u64;
+
+                    if let Some(ref callback) = options.progress_callback {
+                        callback(DownloadState::Progress(downloaded));
+                    }
+                }
+                file.flush().await?;
+
+                hasher.map(ChecksumHasher::finalize)
+            };
+
+            if let (Some(checksum), Some(digest)) = (&options.checksum, digest) {
+                if let Err(err) = checksum.verify(&digest) {
+                    fs::remove_file(&part_path).await.ok();
+                    fs::remove_file(&meta_path).await.ok();
+                    return Err(err);
+                }
+            }
+            if let Some(ref signature) = options.signature {
+                if let Err(err) = signature.verify(&part_path).await {
+                    fs::remove_file(&part_path).await.ok();
+                    fs::remove_file(&meta_path).await.ok();
+                    return Err(err);
                 }
             }
 
@@ -282,17 +517,7 @@ impl Downloader<'_> {
             }
 
             if options.extract_archive {
-                let extract_dir = match &options.extract_dir {
-                    Some(path) => PathBuf::from(path),
-                    None => {
-                        let path = build_absolute_path(&final_target)?;
-                        path.parent()
-                            .map(|p| p.to_path_buf())
-                            .unwrap_or_else(|| PathBuf::from("."))
-                    }
-                };
-                let archive = Archive::new(&final_target)?;
-                archive.extract_to(&extract_dir).await?;
+                extract_archive(&final_target, options.extract_dir.as_deref()).await?;
             }
 
             if let Some(ref callback) = options.progress_callback {
@@ -301,6 +526,287 @@ impl Downloader<'_> {
             return Ok(final_target.to_string_lossy().into());
         }
     }
+
+    /// Downloads the missing bytes of `segments` into `part_path` concurrently. Progress
+    /// is kept in `meta_path` on failure, so a retry resumes where each segment stopped.
+    /// Ranges are requested with `If-Range` on `validator`, if the file changes meanwhile
+    /// the part file is discarded and it fails with a `200 OK` [`DownloadError::ResourceError`].
+    async fn download_segments(
+        &self,
+        url: &Url,
+        part_path: &Path,
+        meta_path: &Path,
+        segments: Vec<SegmentProgress>,
+        validator: (Option<String>, Option<String>),
+        options: &DownloadOptions,
+    ) -> Result<(), DownloadError> {
+        let total_size = segments.last().map_or(0, |last| last.end + 1);
+        let fresh = segments.iter().all(|segment| segment.written == 0);
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(fresh)
+            .open(part_path)
+            .await?;
+        file.set_len(total_size).await?;
+        drop(file);
+
+        let (etag, last_modified) = validator;
+        ResumeSupport::write_segments(
+            meta_path,
+            etag.clone(),
+            last_modified.clone(),
+            segments.clone(),
+        )
+        .await?;
+        // an ETag is a stronger validator than a date
+        let if_range = etag
+            .as_deref()
+            .or(last_modified.as_deref())
+            .and_then(|value| HeaderValue::from_str(value).ok());
+
+        let written = segments
+            .iter()
+            .map(|segment| AtomicU64::new(segment.written))
+            .collect::<Vec<_>>();
+        let downloaded = Arc::new(AtomicU64::new(
+            segments.iter().map(|segment| segment.written).sum(),
+        ));
+        let changed = AtomicBool::new(false);
+
+        let tasks = segments
+            .iter()
+            .zip(&written)
+            .filter(|(segment, _)| segment.start + segment.written <= segment.end)
+            .map(|(segment, written)| {
+                let start = segment.start + written.load(Ordering::Relaxed);
+                let end = segment.end;
+                let downloaded = downloaded.clone();
+                let callback = options.progress_callback.clone();
+                let if_range = if_range.clone();
+                let changed = &changed;
+
+                async move {
+                    let mut request = self
+                        .client
+                        .get(url.clone())
+                        .headers(options.headers.clone().unwrap_or_default())
+                        .header(RANGE, format!("bytes={}-{}", start, end));
+                    if let Some(if_range) = if_range {
+                        request = request.header(IF_RANGE, if_range);
+                    }
+                    let response = request
+                        .send()
+                        .await
+                        .map_err(|err| DownloadError::NetworkError { source: err })?;
+
+                    if response.status() != StatusCode::PARTIAL_CONTENT {
+                        // `If-Range` didn't match, the whole file is sent instead
+                        if response.status() == StatusCode::OK {
+                            changed.store(true, Ordering::Relaxed);
+                        }
+                        return Err(DownloadError::ResourceError {
+                            url: url.to_string(),
+                            status: response.status(),
+                            retry_after: retry_after(response.headers()),
+                        });
+                    }
+
+                    let mut file = OpenOptions::new().write(true).open(part_path).await?;
+                    file.seek(SeekFrom::Start(start)).await?;
+
+                    let mut stream = response.bytes_stream();
+                    while let Some(chunk) = stream
+                        .try_next()
+                        .await
+                        .map_err(|_| DownloadError::ChunkError)?
+                    {
+                        let chunk_size = chunk.len() as u64;
+                        if segment.start + written.load(Ordering::Relaxed) + chunk_size > end + 1 {
+                            return Err(DownloadError::ChunkError);
+                        }
+                        file.write_all(&chunk).await?;
+                        written.fetch_add(chunk_size, Ordering::Relaxed);
+
+                        let current =
+                            downloaded.fetch_add(chunk_size, Ordering::Relaxed) + chunk_size;
+                        if let Some(ref callback) = callback {
+                            callback(DownloadState::Progress(current));
+                        }
+                    }
+                    file.flush().await?;
+
+                    if segment.start + written.load(Ordering::Relaxed) != end + 1 {
+                        return Err(DownloadError::ChunkError);
+                    }
+
+                    Ok(())
+                }
+            });
+
+        if let Err(err) = try_join_all(tasks).await {
+            if changed.load(Ordering::Relaxed) {
+                fs::remove_file(part_path).await.ok();
+                fs::remove_file(meta_path).await.ok();
+            } else {
+                let segments = segments
+                    .into_iter()
+                    .zip(&written)
+                    .map(|(segment, written)| SegmentProgress {
+                        written: written.load(Ordering::Relaxed),
+                        ..segment
+                    })
+                    .collect();
+                ResumeSupport::write_segments(meta_path, etag, last_modified, segments).await?;
+            }
+            return Err(err);
+        }
+        Ok(())
+    }
+}
+
+/// Whether the server says it serves byte ranges.
+fn accepts_ranges(headers: &HeaderMap) -> bool {
+    headers
+        .get(ACCEPT_RANGES)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("bytes"))
+}
+
+/// Splits `total_size` bytes into `count` ranges of about the same size.
+fn segment_ranges(total_size: u64, count: u64) -> Vec<SegmentProgress> {
+    let segment_size = total_size.div_ceil(count);
+    (0..count)
+        .map(|index| index * segment_size)
+        .take_while(|start| *start < total_size)
+        .map(|start| SegmentProgress {
+            start,
+            end: (start + segment_size).min(total_size) - 1,
+            written: 0,
+        })
+        .collect()
+}
+
+/// Number of ranges to split the response into, or `1` for a regular single-stream
+/// download. Each range is at least [`MIN_SEGMENT_SIZE`], and there are at most
+/// [`MAX_SEGMENTS`] of them.
+fn segment_count(
+    status: StatusCode,
+    headers: &HeaderMap,
+    segments: Option<u64>,
+    downloaded: u64,
+    total_size: u64,
+) -> u64 {
+    match segments {
+        Some(segments)
+            if segments > 1
+                && accepts_ranges(headers)
+                && downloaded == 0
+                && status == StatusCode::OK =>
+        {
+            segments
+                .min(MAX_SEGMENTS)
+                .min(total_size / MIN_SEGMENT_SIZE)
+                .max(1)
+        }
+        _ => 1,
+    }
+}
+
+/// Path derived from the URL or `output_path`, and the directory the file goes into
+/// when only a directory was given.
+fn output_paths(options: &DownloadOptions) -> (PathBuf, Option<PathBuf>) {
+    let hash_fallback = || {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(options.url.as_bytes());
+        let result = hasher.finalize();
+        result.to_hex().to_string()
+    };
+
+    if let Some(ref out) = options.output_path {
+        if out.ends_with('/') {
+            let dir = PathBuf::from(out);
+            let base = extract_filename_from_url(&options.url).unwrap_or_else(hash_fallback);
+            (dir.join(&base), Some(dir))
+        } else {
+            let p = PathBuf::from(out);
+            if p.is_dir() {
+                let base = extract_filename_from_url(&options.url).unwrap_or_else(hash_fallback);
+                (p.join(&base), Some(p))
+            } else {
+                (p, None)
+            }
+        }
+    } else {
+        let base = extract_filename_from_url(&options.url).unwrap_or_else(hash_fallback);
+        (PathBuf::from(&base), None)
+    }
+}
+
+fn final_target(
+    options: &DownloadOptions,
+    provisional_path: PathBuf,
+    final_dir: Option<PathBuf>,
+    headers: &HeaderMap,
+) -> PathBuf {
+    let header_name = headers
+        .get(CONTENT_DISPOSITION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(extract_filename);
+
+    match &options.output_path {
+        Some(_) => provisional_path,
+        None => match header_name {
+            Some(ref name) => final_dir
+                .as_ref()
+                .map(|dir| dir.join(name))
+                .unwrap_or_else(|| PathBuf::from(name)),
+            None => provisional_path,
+        },
+    }
+}
+
+impl FileAction {
+    /// What a download to `path` would do with the files already on disk.
+    pub fn resolve<P: AsRef<Path>>(path: P, file_mode: &FileMode) -> Self {
+        let path = path.as_ref();
+        let (part_path, _) = ResumeSupport::get_part_paths(path);
+        if part_path.exists() {
+            return FileAction::Resume;
+        }
+        if !path.exists() {
+            return FileAction::Create;
+        }
+        match file_mode {
+            FileMode::SkipExisting => FileAction::Skip,
+            FileMode::ForceOverwrite => FileAction::Overwrite,
+            FileMode::PromptOverwrite => FileAction::Prompt,
+        }
+    }
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            SkipReason::AlreadyDownloaded => "already downloaded",
+            SkipReason::Untitled => "no title annotation",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+impl Display for FileAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let action = match self {
+            FileAction::Create => "create",
+            FileAction::Resume => "resume",
+            FileAction::Overwrite => "overwrite",
+            FileAction::Prompt => "prompt",
+            FileAction::Skip => "skip",
+            FileAction::Stdout => "stdout",
+        };
+        write!(f, "{}", action)
+    }
 }
 
 pub struct OciDownloader {
@@ -318,23 +824,35 @@ impl OciDownloader {
         }
     }
 
-    pub async fn download_blob(&self, client: OciClient) -> Result<(), DownloadError> {
+    fn blob_path(&self, reference: &Reference) -> String {
+        let ref_name = reference
+            .package
+            .rsplit_once('/')
+            .map_or(reference.tag.clone(), |(_, name)| name.to_string());
+        match self.options.output_path {
+            Some(ref dir) if dir.ends_with('/') => {
+                format!("{}/{}", dir.trim_end_matches('/'), ref_name)
+            }
+            Some(ref path) => path.clone(),
+            None => ref_name,
+        }
+    }
+
+    pub async fn download_blob(&self, client: OciClient) -> Result<String, DownloadError> {
         let options = &self.options;
         let reference = client.reference.clone();
+        let file_path = self.blob_path(&reference);
         let digest = reference.tag;
         let downloaded_bytes = Arc::new(Mutex::new(0u64));
-        let output_path = options.output_path.clone();
-        let ref_name = reference
-            .package
-            .rsplit_once('/')
-            .map_or(digest.clone(), |(_, name)| name.to_string());
-        let file_path = output_path.unwrap_or_else(|| ref_name.clone());
-        let file_path = if file_path.ends_with('/') {
-            fs::create_dir_all(&file_path).await?;
-            format!("{}/{}", file_path.trim_end_matches('/'), ref_name)
-        } else {
-            file_path
-        };
+        if options
+            .output_path
+            .as_ref()
+            .is_some_and(|p| p.ends_with('/'))
+        {
+            if let Some(parent) = Path::new(&file_path).parent() {
+                fs::create_dir_all(parent).await?;
+            }
+        }
 
         let fake_layer = OciLayer {
             media_type: String::from("application/octet-stream"),
@@ -361,28 +879,74 @@ impl OciDownloader {
             callback(DownloadState::Complete);
         }
 
-        Ok(())
+        Ok(file_path)
     }
 
-    pub async fn download_oci(&mut self) -> Result<(), DownloadError> {
+    /// Downloads the matching layers of the image, or the blob the reference names.
+    /// Calling it again after [`DownloadError::LayersFailed`] only retries the layers
+    /// that failed, against the manifest fetched the first time.
+    pub async fn download_oci(&mut self) -> Result<OciDownloadReport, DownloadError> {
         let options = &self.options;
         let url = options.url.clone();
         let reference: Reference = url.into();
-        let oci_client = OciClient::new(
+        let mut oci_client = OciClient::new(
             &reference,
             options.api.clone(),
             self.options.file_mode.clone(),
         );
+        oci_client.retry = options.retry.clone();
+        oci_client.platform = options.platform.clone();
 
         if reference.tag.starts_with("sha256:") {
-            return self.download_blob(oci_client).await;
+            let digest = reference.tag.clone();
+            if options.dry_run {
+                let path = self.blob_path(&reference);
+                return Ok(OciDownloadReport {
+                    layers: vec![LayerResult {
+                        title: digest.clone(),
+                        status: LayerStatus::Planned(DownloadPlan {
+                            url: oci_client.blob_url(&digest),
+                            action: FileAction::resolve(&path, &options.file_mode),
+                            path: path.clone(),
+                            size: None,
+                        }),
+                        digest,
+                        path: PathBuf::from(path),
+                    }],
+                });
+            }
+            let path = self.download_blob(oci_client).await?;
+            return Ok(OciDownloadReport {
+                layers: vec![LayerResult {
+                    title: digest.clone(),
+                    digest,
+                    path: PathBuf::from(path),
+                    status: LayerStatus::Downloaded,
+                }],
+            });
         }
+        // kept for later calls, which only retry the layers that failed
         let manifest = match self.manifest {
             Some(ref manifest) => manifest,
-            None => &oci_client.manifest().await?,
+            None => self.manifest.insert(oci_client.manifest().await?),
         };
 
         let mut tasks = Vec::new();
+        let mut report = OciDownloadReport::default();
+
+        // the title is the file name, untitled layers can't be saved
+        report.layers.extend(
+            manifest
+                .layers
+                .iter()
+                .filter(|layer| layer.get_title().is_none())
+                .map(|layer| LayerResult {
+                    title: layer.digest.clone(),
+                    digest: layer.digest.clone(),
+                    path: PathBuf::new(),
+                    status: LayerStatus::Skipped(SkipReason::Untitled),
+                }),
+        );
 
         let layers = manifest
             .layers
@@ -408,13 +972,41 @@ impl OciDownloader {
             return Err(DownloadError::LayersNotFound);
         }
 
+        if options.dry_run {
+            let base_path = options
+                .output_path
+                .as_ref()
+                .map(PathBuf::from)
+                .unwrap_or_default();
+            report.layers.extend(layers.into_iter().filter_map(|layer| {
+                let title = layer.get_title()?;
+                let path = base_path.join(&title);
+                Some(LayerResult {
+                    status: LayerStatus::Planned(DownloadPlan {
+                        url: oci_client.blob_url(&layer.digest),
+                        path: path.to_string_lossy().into(),
+                        size: Some(layer.size),
+                        action: FileAction::resolve(&path, &options.file_mode),
+                    }),
+                    title,
+                    digest: layer.digest,
+                    path,
+                })
+            }));
+            return Ok(report);
+        }
+
         let total_bytes: u64 = layers.iter().map(|layer| layer.size).sum();
 
         if let Some(ref callback) = options.progress_callback {
             callback(DownloadState::Preparing(total_bytes));
         }
 
-        let semaphore = Arc::new(Semaphore::new(options.concurrency.unwrap_or(1) as usize));
+        let scheduler = Scheduler::new(
+            options
+                .concurrency
+                .map_or(layers.len().min(DEFAULT_OCI_CONCURRENCY), |c| c as usize),
+        );
         let downloaded_bytes = Arc::new(Mutex::new(0u64));
         let outdir = options.output_path.clone();
         let base_path = if let Some(dir) = outdir {
@@ -425,26 +1017,32 @@ impl OciDownloader {
         };
 
         for layer in layers {
+            let Some(filename) = layer.get_title() else {
+                continue;
+            };
             if self
                 .completed_layers
                 .lock()
                 .unwrap()
                 .contains(&layer.digest)
             {
+                report.layers.push(LayerResult {
+                    path: base_path.join(&filename),
+                    title: filename,
+                    digest: layer.digest,
+                    status: LayerStatus::Skipped(SkipReason::AlreadyDownloaded),
+                });
                 continue;
             }
-            let permit = semaphore.clone().acquire_owned().await.unwrap();
             let client_clone = oci_client.clone();
             let cb_clone = options.progress_callback.clone();
             let downloaded_bytes = downloaded_bytes.clone();
             let completed_layers = self.completed_layers.clone();
-            let Some(filename) = layer.get_title() else {
-                continue;
-            };
 
-            let file_path = base_path.join(filename);
+            let file_path = base_path.join(&filename);
+            let digest = layer.digest.clone();
 
-            let task = task::spawn(async move {
+            let task = scheduler.spawn(async move {
                 client_clone
                     .pull_layer(&layer, &file_path, move |bytes, _| {
                         if let Some(ref callback) = cb_clone {
@@ -458,16 +1056,162 @@ impl OciDownloader {
 
                 Ok::<(), DownloadError>(())
             });
-            drop(permit);
-            tasks.push(task);
+            tasks.push((base_path.join(&filename), filename, digest, task));
         }
 
-        let _ = join_all(tasks).await;
+        for (path, title, digest, task) in tasks {
+            let status = match task.await {
+                Ok(Ok(())) => LayerStatus::Downloaded,
+                Ok(Err(err)) => LayerStatus::Failed(err),
+                Err(err) => LayerStatus::Failed(DownloadError::TaskError(err.to_string())),
+            };
+            report.layers.push(LayerResult {
+                title,
+                digest,
+                path,
+                status,
+            });
+        }
+
+        if report.has_failures() {
+            if let Some(ref callback) = options.progress_callback {
+                callback(DownloadState::Error);
+            }
+            return Err(DownloadError::LayersFailed(report));
+        }
 
         if let Some(ref callback) = options.progress_callback {
             callback(DownloadState::Complete);
         }
 
-        Ok(())
+        Ok(report)
+    }
+}
+
+impl OciDownloadReport {
+    pub fn failed(&self) -> impl Iterator<Item = (&LayerResult, &DownloadError)> {
+        self.layers.iter().filter_map(|layer| match layer.status {
+            LayerStatus::Failed(ref err) => Some((layer, err)),
+            _ => None,
+        })
+    }
+
+    pub fn has_failures(&self) -> bool {
+        self.failed().next().is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = MIN_SEGMENT_SIZE;
+
+    fn ranges_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        headers
+    }
+
+    fn count(segments: Option<u64>, total_size: u64) -> u64 {
+        segment_count(StatusCode::OK, &ranges_headers(), segments, 0, total_size)
+    }
+
+    fn bounds(ranges: &[SegmentProgress]) -> Vec<(u64, u64)> {
+        ranges
+            .iter()
+            .map(|range| (range.start, range.end))
+            .collect()
+    }
+
+    #[test]
+    fn segment_count_below_threshold() {
+        assert_eq!(count(Some(4), MIB - 1), 1);
+        assert_eq!(count(Some(4), MIB), 1);
+        assert_eq!(count(Some(4), 2 * MIB), 2);
+        assert_eq!(count(Some(4), 3 * MIB - 1), 2);
+    }
+
+    #[test]
+    fn segment_count_limits() {
+        assert_eq!(count(None, 64 * MIB), 1);
+        assert_eq!(count(Some(1), 64 * MIB), 1);
+        assert_eq!(count(Some(4), 64 * MIB), 4);
+        assert_eq!(count(Some(100), 64 * MIB), MAX_SEGMENTS);
+    }
+
+    #[test]
+    fn segment_count_needs_fresh_ranged_response() {
+        let headers = ranges_headers();
+        assert_eq!(
+            segment_count(StatusCode::OK, &HeaderMap::new(), Some(4), 0, 8 * MIB),
+            1
+        );
+        assert_eq!(
+            segment_count(StatusCode::OK, &headers, Some(4), MIB, 8 * MIB),
+            1
+        );
+        assert_eq!(
+            segment_count(StatusCode::PARTIAL_CONTENT, &headers, Some(4), 0, 8 * MIB),
+            1
+        );
+    }
+
+    #[test]
+    fn segment_ranges_exact_multiple() {
+        assert_eq!(
+            bounds(&segment_ranges(4 * MIB, 4)),
+            [
+                (0, MIB - 1),
+                (MIB, 2 * MIB - 1),
+                (2 * MIB, 3 * MIB - 1),
+                (3 * MIB, 4 * MIB - 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn segment_ranges_remainder() {
+        assert_eq!(bounds(&segment_ranges(10, 3)), [(0, 3), (4, 7), (8, 9)]);
+        // rounding up the size may leave nothing for the last ranges
+        assert_eq!(bounds(&segment_ranges(9, 4)), [(0, 2), (3, 5), (6, 8)]);
+        assert_eq!(bounds(&segment_ranges(10, 1)), [(0, 9)]);
+        assert!(segment_ranges(10, 3).iter().all(|range| range.written == 0));
+    }
+
+    #[test]
+    fn file_action_resolve() {
+        let dir = std::env::temp_dir().join(format!("soar-dl-plan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tool");
+
+        assert_eq!(
+            FileAction::resolve(&path, &FileMode::SkipExisting),
+            FileAction::Create
+        );
+
+        std::fs::write(&path, b"hello").unwrap();
+        assert_eq!(
+            FileAction::resolve(&path, &FileMode::SkipExisting),
+            FileAction::Skip
+        );
+        assert_eq!(
+            FileAction::resolve(&path, &FileMode::ForceOverwrite),
+            FileAction::Overwrite
+        );
+        assert_eq!(
+            FileAction::resolve(&path, &FileMode::PromptOverwrite),
+            FileAction::Prompt
+        );
+
+        // an interrupted download is resumed whatever the file mode
+        let (part_path, _) = ResumeSupport::get_part_paths(&path);
+        std::fs::write(&part_path, b"he").unwrap();
+        assert_eq!(
+            FileAction::resolve(&path, &FileMode::SkipExisting),
+            FileAction::Resume
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
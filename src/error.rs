@@ -1,4 +1,6 @@
-use std::{error::Error, fmt::Display, io};
+use std::{error::Error, fmt::Display, io, time::Duration};
+
+use crate::downloader::OciDownloadReport;
 
 #[derive(Debug)]
 pub enum DownloadError {
@@ -13,12 +15,28 @@ pub enum DownloadError {
     ResourceError {
         url: String,
         status: reqwest::StatusCode,
+        retry_after: Option<Duration>,
     },
     InvalidResponse,
     LayersNotFound,
     ChunkError,
     FileNameNotFound,
     ArchiveError(compak::ArchiveError),
+    ChecksumMismatch {
+        expected: String,
+        actual: String,
+    },
+    SizeMismatch {
+        expected: u64,
+        actual: u64,
+    },
+    SignatureError(String),
+    NoMatchingPlatform {
+        platform: String,
+        available: Vec<String>,
+    },
+    TaskError(String),
+    LayersFailed(OciDownloadReport),
 }
 
 impl Display for DownloadError {
@@ -27,7 +45,7 @@ impl Display for DownloadError {
             DownloadError::IoError(err) => write!(f, "IO error: {}", err),
             DownloadError::InvalidUrl { url, .. } => write!(f, "Invalid URL: {}", url),
             DownloadError::NetworkError { .. } => write!(f, "Network Request failed"),
-            DownloadError::ResourceError { url, status } => {
+            DownloadError::ResourceError { url, status, .. } => {
                 write!(f, "Failed to fetch resource from {} [{}]", url, status)
             }
             DownloadError::LayersNotFound => write!(f, "No downloadable layers found"),
@@ -40,6 +58,46 @@ impl Display for DownloadError {
                 )
             }
             DownloadError::ArchiveError(err) => write!(f, "Archive error: {}", err),
+            DownloadError::ChecksumMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Checksum mismatch: expected {}, got {}",
+                    expected, actual
+                )
+            }
+            DownloadError::SizeMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "Size mismatch: expected {} bytes, got {} bytes",
+                    expected, actual
+                )
+            }
+            DownloadError::SignatureError(msg) => {
+                write!(f, "Signature verification failed: {}", msg)
+            }
+            DownloadError::NoMatchingPlatform {
+                platform,
+                available,
+            } => write!(
+                f,
+                "No manifest found for platform {} (available: {})",
+                platform,
+                available.join(", ")
+            ),
+            DownloadError::TaskError(msg) => write!(f, "Download task failed: {}", msg),
+            DownloadError::LayersFailed(report) => {
+                let failed = report
+                    .failed()
+                    .map(|(layer, err)| format!("{}: {}", layer.title, err))
+                    .collect::<Vec<_>>();
+                write!(
+                    f,
+                    "{} of {} layers failed ({})",
+                    failed.len(),
+                    report.layers.len(),
+                    failed.join("; ")
+                )
+            }
         }
     }
 }
@@ -70,13 +128,32 @@ impl From<compak::ArchiveError> for DownloadError {
 
 #[derive(Debug)]
 pub enum PlatformError {
-    ApiError { status: reqwest::StatusCode },
+    ApiError {
+        status: reqwest::StatusCode,
+    },
     DownloadError(DownloadError),
     InvalidInput(String),
     InvalidResponse,
-    NoMatchingAssets { available_assets: Vec<String> },
-    NoRelease { tag: Option<String> },
-    RepositoryNotFound { owner: String, repo: String },
+    NoMatchingAssets {
+        available_assets: Vec<String>,
+    },
+    ChecksumNotFound {
+        asset: String,
+        checksum_file: String,
+    },
+    NoRelease {
+        tag: Option<String>,
+    },
+    NoWorkflowRun {
+        branch: Option<String>,
+    },
+    RepositoryNotFound {
+        owner: String,
+        repo: String,
+    },
+    SignatureNotFound {
+        asset: String,
+    },
 }
 
 impl Display for PlatformError {
@@ -96,9 +173,20 @@ impl Display for PlatformError {
                     .unwrap_or("release".to_string())
             ),
             PlatformError::NoMatchingAssets { .. } => write!(f, "No matching assets found"),
+            PlatformError::ChecksumNotFound {
+                asset,
+                checksum_file,
+            } => write!(f, "{} has no checksum for {}", checksum_file, asset),
+            PlatformError::NoWorkflowRun { branch } => match branch {
+                Some(branch) => write!(f, "No matching workflow run found on {}", branch),
+                None => write!(f, "No matching workflow run found"),
+            },
             PlatformError::RepositoryNotFound { owner, repo } => {
                 write!(f, "Repository not found: {}/{}", owner, repo)
             }
+            PlatformError::SignatureNotFound { asset } => {
+                write!(f, "No signature found for {}", asset)
+            }
         }
     }
 }
@@ -0,0 +1,138 @@
+use serde::Deserialize;
+
+use crate::{
+    error::PlatformError,
+    platform::{
+        split_instance, ForgeHost, ForgeKind, Release, ReleaseAsset, ReleasePlatform, RepoSource,
+    },
+    utils::{encode_uri_component, encode_uri_path},
+};
+
+/// Gitea and Forgejo instances, Codeberg by default. Use
+/// [`ReleaseHandler::with_instance`](crate::platform::ReleaseHandler::with_instance)
+/// for other instances.
+pub struct Gitea;
+impl ReleasePlatform for Gitea {
+    const API_BASE_PRIMARY: &'static str = "https://codeberg.org";
+
+    const API_BASE_PKGFORGE: Option<&'static str> = None;
+
+    const TOKEN_ENV_VAR: &'static str = "GITEA_TOKEN";
+    const FORGE_KIND: Option<ForgeKind> = Some(ForgeKind::Gitea);
+
+    fn format_project_path(project: &str) -> Result<(String, String), PlatformError> {
+        match project.split_once('/') {
+            Some((owner, repo))
+                if !owner.trim().is_empty() && !repo.trim().is_empty() && !repo.contains('/') =>
+            {
+                Ok((owner.to_string(), repo.to_string()))
+            }
+            _ => Err(PlatformError::InvalidInput(format!(
+                "Gitea project '{}' must be in 'owner/repo' format",
+                project
+            ))),
+        }
+    }
+
+    fn format_api_path(project: &str, tag: Option<&str>) -> Result<String, PlatformError> {
+        let (owner, repo) = Self::format_project_path(project)?;
+        let base_path = format!("/api/v1/repos/{}/{}/releases", owner, repo);
+        if let Some(tag) = tag {
+            Ok(format!("{}/tags/{}", base_path, encode_uri_component(tag)))
+        } else {
+            Ok(format!("{}?limit=50", base_path))
+        }
+    }
+
+    fn format_source_path(
+        project: &str,
+        git_ref: &str,
+        source: &RepoSource,
+    ) -> Result<String, PlatformError> {
+        let (owner, repo) = Self::format_project_path(project)?;
+        let base_path = format!("/api/v1/repos/{}/{}", owner, repo);
+        Ok(match source {
+            RepoSource::Archive(format) => format!(
+                "{}/archive/{}.{}",
+                base_path,
+                encode_uri_path(git_ref),
+                format.extension()
+            ),
+            RepoSource::File(path) => format!(
+                "{}/raw/{}?ref={}",
+                base_path,
+                encode_uri_path(path),
+                encode_uri_component(git_ref)
+            ),
+        })
+    }
+
+    /// Projects are always `owner/repo`, so `host/owner/repo` names an instance even if
+    /// the host isn't one of `hosts`.
+    fn split_instance<'a>(project: &'a str, hosts: &[ForgeHost]) -> (Option<String>, &'a str) {
+        let (instance, project) = split_instance(project, hosts);
+        if instance.is_some() {
+            return (instance, project);
+        }
+
+        let path = project.split('@').next().unwrap_or_default();
+        match project.split_once('/') {
+            Some((host, project)) if path.matches('/').count() >= 2 => {
+                (Some(format!("https://{}", host)), project)
+            }
+            _ => (None, project),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GiteaRelease {
+    name: Option<String>,
+    tag_name: String,
+    prerelease: bool,
+    published_at: String,
+    assets: Vec<GiteaAsset>,
+}
+
+impl Release<GiteaAsset> for GiteaRelease {
+    fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("")
+    }
+
+    fn tag_name(&self) -> &str {
+        &self.tag_name
+    }
+
+    fn is_prerelease(&self) -> bool {
+        self.prerelease
+    }
+
+    fn published_at(&self) -> &str {
+        &self.published_at
+    }
+
+    fn assets(&self) -> Vec<GiteaAsset> {
+        self.assets.clone()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct GiteaAsset {
+    pub name: String,
+    pub size: u64,
+    pub browser_download_url: String,
+}
+
+impl ReleaseAsset for GiteaAsset {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn size(&self) -> Option<u64> {
+        Some(self.size)
+    }
+
+    fn download_url(&self) -> &str {
+        &self.browser_download_url
+    }
+}
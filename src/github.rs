@@ -1,17 +1,23 @@
 use serde::Deserialize;
+use url::form_urlencoded;
 
 use crate::{
     error::PlatformError,
-    platform::{Release, ReleaseAsset, ReleasePlatform},
+    platform::{
+        ArchiveFormat, ForgeKind, Release, ReleaseAsset, ReleaseHandler, ReleasePlatform,
+        RepoSource,
+    },
+    utils::{encode_uri_component, encode_uri_path},
 };
 
 pub struct Github;
 impl ReleasePlatform for Github {
     const API_BASE_PRIMARY: &'static str = "https://api.github.com";
 
-    const API_BASE_PKGFORGE: &'static str = "https://api.gh.pkgforge.dev";
+    const API_BASE_PKGFORGE: Option<&'static str> = Some("https://api.gh.pkgforge.dev");
 
     const TOKEN_ENV_VAR: &'static str = "GITHUB_TOKEN";
+    const FORGE_KIND: Option<ForgeKind> = Some(ForgeKind::Github);
 
     fn format_project_path(project: &str) -> Result<(String, String), PlatformError> {
         match project.split_once('/') {
@@ -25,15 +31,58 @@ impl ReleasePlatform for Github {
         }
     }
 
+    fn instance_api_base(instance_url: &str) -> String {
+        // GitHub Enterprise Server
+        format!("{}/api/v3", instance_url.trim_end_matches('/'))
+    }
+
     fn format_api_path(project: &str, tag: Option<&str>) -> Result<String, PlatformError> {
         let (owner, repo) = Self::format_project_path(project)?;
         let base_path = format!("/repos/{}/{}/releases", owner, repo);
         if let Some(tag) = tag {
-            Ok(format!("{}/tags/{}?per_page=100", base_path, tag))
+            Ok(format!(
+                "{}/tags/{}?per_page=100",
+                base_path,
+                encode_uri_component(tag)
+            ))
         } else {
             Ok(format!("{}?per_page=100", base_path))
         }
     }
+
+    fn format_source_path(
+        project: &str,
+        git_ref: &str,
+        source: &RepoSource,
+    ) -> Result<String, PlatformError> {
+        let (owner, repo) = Self::format_project_path(project)?;
+        Ok(match source {
+            RepoSource::Archive(ArchiveFormat::TarGz) => format!(
+                "/repos/{}/{}/tarball/{}",
+                owner,
+                repo,
+                encode_uri_path(git_ref)
+            ),
+            RepoSource::Archive(ArchiveFormat::Zip) => format!(
+                "/repos/{}/{}/zipball/{}",
+                owner,
+                repo,
+                encode_uri_path(git_ref)
+            ),
+            RepoSource::File(path) => format!(
+                "/repos/{}/{}/contents/{}?ref={}",
+                owner,
+                repo,
+                encode_uri_path(path),
+                encode_uri_component(git_ref)
+            ),
+        })
+    }
+
+    fn source_accept(source: &RepoSource) -> Option<&'static str> {
+        // the contents API returns JSON metadata otherwise
+        matches!(source, RepoSource::File(_)).then_some("application/vnd.github.raw")
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -87,3 +136,124 @@ impl ReleaseAsset for GithubAsset {
         &self.browser_download_url
     }
 }
+
+/// Which workflow runs artifacts are taken from.
+#[derive(Clone, Debug, Default)]
+pub struct WorkflowRunFilter {
+    /// Workflow file name, e.g. `nightly.yml`. Runs of any workflow if not set
+    pub workflow: Option<String>,
+    pub branch: Option<String>,
+    /// Status or conclusion of the run, `success` if not set
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkflowRuns {
+    workflow_runs: Vec<WorkflowRun>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WorkflowRun {
+    pub id: u64,
+    pub name: Option<String>,
+    pub run_number: u64,
+    pub head_branch: Option<String>,
+    pub head_sha: String,
+    pub conclusion: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Artifacts {
+    artifacts: Vec<GithubArtifact>,
+}
+
+/// Actions artifact, a zip of the files uploaded by a workflow run.
+#[derive(Clone, Debug, Deserialize)]
+pub struct GithubArtifact {
+    pub id: u64,
+    pub name: String,
+    pub size_in_bytes: u64,
+    pub archive_download_url: String,
+    pub expired: bool,
+    /// `sha256:<hex>` of the zip, missing for artifacts uploaded by older versions of
+    /// `actions/upload-artifact`
+    pub digest: Option<String>,
+}
+
+impl ReleaseAsset for GithubArtifact {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn size(&self) -> Option<u64> {
+        Some(self.size_in_bytes)
+    }
+
+    fn download_url(&self) -> &str {
+        &self.archive_download_url
+    }
+
+    // even for public repositories
+    fn requires_auth(&self) -> bool {
+        true
+    }
+
+    fn file_name(&self) -> Option<String> {
+        Some(format!("{}.zip", self.name))
+    }
+}
+
+impl ReleaseHandler<'_, Github> {
+    /// Fetches the latest workflow runs of `project` matching `filter`, newest first.
+    pub async fn fetch_workflow_runs(
+        &self,
+        project: &str,
+        filter: &WorkflowRunFilter,
+    ) -> Result<Vec<WorkflowRun>, PlatformError> {
+        let (owner, repo) = Github::format_project_path(project)?;
+        let path = match filter.workflow {
+            Some(ref workflow) => format!(
+                "/repos/{}/{}/actions/workflows/{}/runs",
+                owner,
+                repo,
+                encode_uri_component(workflow)
+            ),
+            None => format!("/repos/{}/{}/actions/runs", owner, repo),
+        };
+        let query = {
+            let mut query = form_urlencoded::Serializer::new(String::new());
+            query
+                .append_pair("per_page", "10")
+                .append_pair("status", filter.status.as_deref().unwrap_or("success"));
+            if let Some(ref branch) = filter.branch {
+                query.append_pair("branch", branch);
+            }
+            query.finish()
+        };
+        let path = format!("{}?{}", path, query);
+
+        let runs: WorkflowRuns = self.fetch_json(&path).await?;
+        Ok(runs.workflow_runs)
+    }
+
+    /// Fetches the artifacts of the workflow run `run_id` that haven't expired.
+    pub async fn fetch_artifacts(
+        &self,
+        project: &str,
+        run_id: u64,
+    ) -> Result<Vec<GithubArtifact>, PlatformError> {
+        let (owner, repo) = Github::format_project_path(project)?;
+        let path = format!(
+            "/repos/{}/{}/actions/runs/{}/artifacts?per_page=100",
+            owner, repo, run_id
+        );
+
+        let artifacts: Artifacts = self.fetch_json(&path).await?;
+        Ok(artifacts
+            .artifacts
+            .into_iter()
+            .filter(|artifact| !artifact.expired)
+            .collect())
+    }
+}
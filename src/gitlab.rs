@@ -2,16 +2,18 @@ use serde::Deserialize;
 
 use crate::{
     error::PlatformError,
-    platform::{Release, ReleaseAsset, ReleasePlatform},
+    platform::{ForgeKind, Release, ReleaseAsset, ReleasePlatform, RepoSource},
+    utils::encode_uri_component,
 };
 
 pub struct Gitlab;
 impl ReleasePlatform for Gitlab {
     const API_BASE_PRIMARY: &'static str = "https://gitlab.com";
 
-    const API_BASE_PKGFORGE: &'static str = "https://api.gl.pkgforge.dev";
+    const API_BASE_PKGFORGE: Option<&'static str> = Some("https://api.gl.pkgforge.dev");
 
     const TOKEN_ENV_VAR: &'static str = "GITLAB_TOKEN";
+    const FORGE_KIND: Option<ForgeKind> = Some(ForgeKind::Gitlab);
 
     fn format_project_path(project: &str) -> Result<(String, String), PlatformError> {
         if project.chars().all(|c| c.is_numeric()) {
@@ -31,10 +33,37 @@ impl ReleasePlatform for Gitlab {
 
         if let Some(tag) = tag {
             if project.chars().all(char::is_numeric) {
-                return Ok(format!("{}/{}", base_path, tag));
+                return Ok(format!("{}/{}", base_path, encode_uri_component(tag)));
             }
         }
-        Ok(base_path)
+        Ok(format!("{}?per_page=100", base_path))
+    }
+
+    fn format_source_path(
+        project: &str,
+        git_ref: &str,
+        source: &RepoSource,
+    ) -> Result<String, PlatformError> {
+        let base_path = format!(
+            "/api/v4/projects/{}/repository",
+            project.replace('/', "%2F")
+        );
+        Ok(match source {
+            RepoSource::Archive(format) => {
+                format!(
+                    "{}/archive.{}?sha={}",
+                    base_path,
+                    format.extension(),
+                    encode_uri_component(git_ref)
+                )
+            }
+            RepoSource::File(path) => format!(
+                "{}/files/{}/raw?ref={}",
+                base_path,
+                encode_uri_component(path),
+                encode_uri_component(git_ref)
+            ),
+        })
     }
 }
 
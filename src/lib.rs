@@ -1,9 +1,16 @@
+pub mod checksum;
 pub mod downloader;
 pub mod error;
+pub mod gitea;
 pub mod github;
 pub mod gitlab;
 pub mod http_client;
 pub mod oci;
+pub mod oci_auth;
 pub mod platform;
 pub mod resume;
+pub mod retry;
+pub mod scheduler;
+pub mod signature;
 pub mod utils;
+pub mod version;
@@ -1,17 +1,34 @@
-use std::path::Path;
-use std::sync::Arc;
 use std::{collections::HashMap, fs::Permissions, os::unix::fs::PermissionsExt};
+use std::{
+    fmt::Display,
+    path::Path,
+    sync::{Arc, RwLock},
+};
 
 use futures::TryStreamExt;
-use reqwest::header::{self, HeaderMap, ETAG, LAST_MODIFIED};
+use reqwest::{
+    header::{self, HeaderMap, HeaderValue, ETAG, LAST_MODIFIED},
+    Response, StatusCode,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use tokio::{
     fs::{self, OpenOptions},
     io::AsyncWriteExt,
+    time::sleep,
 };
 
-use crate::utils::FileMode;
-use crate::{error::DownloadError, resume::ResumeSupport, utils::is_elf};
+use crate::utils::{FileMode, PromptCallback};
+use url::Url;
+
+use crate::{
+    checksum::{Checksum, ChecksumHasher},
+    error::DownloadError,
+    oci_auth::{AuthChallenge, RegistryCredentials},
+    resume::ResumeSupport,
+    retry::{retry_after, RetryPolicy},
+    utils::is_elf,
+};
 
 #[derive(Clone, Deserialize)]
 pub struct OciLayer {
@@ -38,13 +55,42 @@ pub struct OciManifest {
     pub layers: Vec<OciLayer>,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+pub struct OciPlatform {
+    pub os: String,
+    pub architecture: String,
+    #[serde(default)]
+    pub variant: Option<String>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct OciIndexEntry {
+    #[serde(rename = "mediaType", default)]
+    pub media_type: String,
+    pub digest: String,
+    pub size: u64,
+    #[serde(default)]
+    pub platform: Option<OciPlatform>,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct OciIndex {
+    #[serde(rename = "mediaType", default)]
+    pub media_type: String,
+    pub manifests: Vec<OciIndexEntry>,
+}
+
 #[derive(Clone)]
 pub struct OciClient {
     client: reqwest::Client,
     pub reference: Reference,
     pub api: Option<String>,
     pub file_mode: FileMode,
-    pub prompt: Option<Arc<dyn Fn(&str) -> Result<bool, DownloadError> + Send + Sync + 'static>>,
+    pub prompt: Option<PromptCallback>,
+    pub retry: Option<RetryPolicy>,
+    pub platform: Option<OciPlatform>,
+    pub credentials: Option<RegistryCredentials>,
+    authorization: Arc<RwLock<Option<String>>>,
 }
 
 #[derive(Clone, Debug)]
@@ -58,39 +104,72 @@ pub struct OciDownloadProgress {
 
 #[derive(Clone)]
 pub struct Reference {
+    pub registry: String,
     pub package: String,
     pub tag: String,
 }
 
+pub const DOCKER_HUB_REGISTRY: &str = "docker.io";
+
 #[derive(Deserialize, Serialize)]
 pub struct LayerMeta {
     etag: Option<String>,
     last_modified: Option<String>,
 }
 
+impl Reference {
+    pub fn api_base(&self) -> String {
+        match self.registry.as_str() {
+            DOCKER_HUB_REGISTRY => "https://registry-1.docker.io/v2".to_string(),
+            registry if registry.starts_with("localhost") || registry.starts_with("127.") => {
+                format!("http://{}/v2", registry)
+            }
+            registry => format!("https://{}/v2", registry),
+        }
+    }
+}
+
+/// Whether the first path component of a reference names a registry host rather than
+/// a Docker Hub namespace.
+pub fn is_registry_host(component: &str) -> bool {
+    component.contains('.') || component.contains(':') || component == "localhost"
+}
+
 impl From<&str> for Reference {
     fn from(value: &str) -> Self {
-        let paths = value.trim_start_matches("ghcr.io/");
+        let value = value.trim().trim_start_matches("oci://");
 
-        // <package>@sha256:<digest>
-        if let Some((package, digest)) = paths.split_once("@") {
-            return Self {
-                package: package.to_string(),
-                tag: digest.to_string(),
-            };
-        }
+        let (registry, remainder) = match value.split_once('/') {
+            Some((host, rest)) if is_registry_host(host) => (host.to_string(), rest),
+            _ => (DOCKER_HUB_REGISTRY.to_string(), value),
+        };
+        let registry = match registry.as_str() {
+            "index.docker.io" | "registry-1.docker.io" => DOCKER_HUB_REGISTRY.to_string(),
+            _ => registry,
+        };
 
-        // <package>:<tag>
-        if let Some((package, tag)) = paths.split_once(':') {
-            return Self {
-                package: package.to_string(),
-                tag: tag.to_string(),
-            };
-        }
+        // <package>[:<tag>]@sha256:<digest>
+        let (name, digest) = match remainder.split_once('@') {
+            Some((name, digest)) => (name, Some(digest)),
+            None => (remainder, None),
+        };
+
+        // <package>:<tag>, the tag can only appear after the last `/`
+        let (package, tag) = match name.rsplit_once(':') {
+            Some((package, tag)) if !tag.contains('/') => (package, Some(tag)),
+            _ => (name, None),
+        };
+
+        let package = if registry == DOCKER_HUB_REGISTRY && !package.contains('/') {
+            format!("library/{}", package)
+        } else {
+            package.to_string()
+        };
 
         Self {
-            package: paths.to_string(),
-            tag: "latest".to_string(),
+            registry,
+            package,
+            tag: digest.or(tag).unwrap_or("latest").to_string(),
         }
     }
 }
@@ -104,13 +183,52 @@ impl From<String> for Reference {
 impl OciClient {
     pub fn new(reference: &Reference, api: Option<String>, file_mode: FileMode) -> Self {
         let client = reqwest::Client::new();
-        Self {
+        let mut oci_client = Self {
             client,
             reference: reference.clone(),
             api,
             file_mode,
             prompt: None,
-        }
+            retry: None,
+            platform: None,
+            credentials: None,
+            authorization: Arc::new(RwLock::new(None)),
+        };
+        oci_client.credentials = RegistryCredentials::resolve(&oci_client.registry());
+        oci_client
+    }
+
+    pub fn api_base(&self) -> String {
+        self.api
+            .clone()
+            .unwrap_or_else(|| self.reference.api_base())
+            .trim_end_matches('/')
+            .to_string()
+    }
+
+    pub fn registry(&self) -> String {
+        let Some(ref api) = self.api else {
+            return self.reference.registry.clone();
+        };
+
+        Url::parse(api)
+            .ok()
+            .and_then(|url| {
+                url.host_str().map(|host| match url.port() {
+                    Some(port) => format!("{}:{}", host, port),
+                    None => host.to_string(),
+                })
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn blob_url(&self, digest: &str) -> String {
+        format!(
+            "{}/{}/blobs/{}",
+            self.api_base(),
+            self.reference.package,
+            digest
+        )
     }
 
     pub fn headers(&self) -> HeaderMap {
@@ -125,40 +243,177 @@ impl OciClient {
                 .parse()
                 .unwrap(),
         );
-        header_map.insert(header::AUTHORIZATION, "Bearer QQ==".parse().unwrap());
+        if let Some(ref authorization) = *self.authorization.read().unwrap() {
+            if let Ok(value) = HeaderValue::from_str(authorization) {
+                header_map.insert(header::AUTHORIZATION, value);
+            }
+        }
         header_map
     }
 
+    /// Sends a GET request, answering a `401` auth challenge once if the registry
+    /// asks for it.
+    async fn send(&self, url: &str, headers: HeaderMap) -> Result<Response, DownloadError> {
+        let response = self
+            .client
+            .get(url)
+            .headers(headers.clone())
+            .send()
+            .await
+            .map_err(|err| DownloadError::NetworkError { source: err })?;
+
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        let Some(challenge) = response
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .and_then(|h| h.to_str().ok())
+            .and_then(AuthChallenge::parse)
+        else {
+            return Ok(response);
+        };
+
+        match (challenge.scheme.as_str(), &self.credentials) {
+            ("bearer", _) => self.send_with_bearer(url, headers, &challenge).await,
+            ("basic", Some(credentials)) => {
+                self.send_authorized(url, headers, credentials.basic_auth())
+                    .await
+            }
+            _ => Ok(response),
+        }
+    }
+
+    /// Answers a bearer `challenge`, with an anonymous token if the credentials are
+    /// rejected. Public images don't need them, and e.g. a `GITHUB_TOKEN` without
+    /// `read:packages` isn't accepted by ghcr.io.
+    async fn send_with_bearer(
+        &self,
+        url: &str,
+        headers: HeaderMap,
+        challenge: &AuthChallenge,
+    ) -> Result<Response, DownloadError> {
+        let scope = format!("repository:{}:pull", self.reference.package);
+        // don't hand credentials to whatever realm the registry names
+        let credentials = self
+            .credentials
+            .as_ref()
+            .filter(|_| challenge.is_realm_trusted(&self.registry()));
+
+        let mut rejected = None;
+        if let Some(credentials) = credentials {
+            match challenge
+                .fetch_token(&self.client, &scope, Some(credentials))
+                .await
+            {
+                Ok(token) => {
+                    let response = self
+                        .send_authorized(url, headers.clone(), format!("Bearer {}", token))
+                        .await?;
+                    if !matches!(
+                        response.status(),
+                        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+                    ) {
+                        return Ok(response);
+                    }
+                }
+                Err(err) => rejected = Some(err),
+            }
+        }
+
+        let token = match challenge.fetch_token(&self.client, &scope, None).await {
+            Ok(token) => token,
+            Err(err) => return Err(rejected.unwrap_or(err)),
+        };
+        self.send_authorized(url, headers, format!("Bearer {}", token))
+            .await
+    }
+
+    /// Sends a GET request with `authorization`, which is kept for later requests.
+    async fn send_authorized(
+        &self,
+        url: &str,
+        mut headers: HeaderMap,
+        authorization: String,
+    ) -> Result<Response, DownloadError> {
+        let value =
+            HeaderValue::from_str(&authorization).map_err(|_| DownloadError::InvalidResponse)?;
+        *self.authorization.write().unwrap() = Some(authorization);
+        headers.insert(header::AUTHORIZATION, value);
+
+        self.client
+            .get(url)
+            .headers(headers)
+            .send()
+            .await
+            .map_err(|err| DownloadError::NetworkError { source: err })
+    }
+
     pub async fn manifest(&self) -> Result<OciManifest, DownloadError> {
+        let mut attempt = 1;
+        loop {
+            match self.try_manifest().await {
+                Err(err) => {
+                    let Some(delay) = self
+                        .retry
+                        .as_ref()
+                        .and_then(|policy| policy.next_delay(attempt, &err))
+                    else {
+                        return Err(err);
+                    };
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn try_manifest(&self) -> Result<OciManifest, DownloadError> {
+        let value = self.fetch_manifest(&self.reference.tag).await?;
+        if !is_index(&value) {
+            return serde_json::from_value(value).map_err(|_| DownloadError::InvalidResponse);
+        }
+
+        let index: OciIndex =
+            serde_json::from_value(value).map_err(|_| DownloadError::InvalidResponse)?;
+        let platform = self.platform.clone().unwrap_or_else(OciPlatform::host);
+        let Some(entry) = index.select(&platform) else {
+            return Err(DownloadError::NoMatchingPlatform {
+                platform: platform.to_string(),
+                available: index
+                    .manifests
+                    .iter()
+                    .filter_map(|m| m.platform.as_ref().map(ToString::to_string))
+                    .collect(),
+            });
+        };
+
+        let value = self.fetch_manifest(&entry.digest).await?;
+        serde_json::from_value(value).map_err(|_| DownloadError::InvalidResponse)
+    }
+
+    async fn fetch_manifest(&self, reference: &str) -> Result<Value, DownloadError> {
         let manifest_url = format!(
             "{}/{}/manifests/{}",
-            self.api
-                .clone()
-                .unwrap_or("https://ghcr.io/v2".to_string())
-                .trim_end_matches('/'),
+            self.api_base(),
             self.reference.package,
-            self.reference.tag
+            reference
         );
-        let resp = self
-            .client
-            .get(&manifest_url)
-            .headers(self.headers())
-            .send()
-            .await
-            .map_err(|err| DownloadError::NetworkError { source: err })?;
+        let resp = self.send(&manifest_url, self.headers()).await?;
 
         if !resp.status().is_success() {
             return Err(DownloadError::ResourceError {
                 status: resp.status(),
                 url: manifest_url,
+                retry_after: retry_after(resp.headers()),
             });
         }
 
-        let manifest: OciManifest = resp
-            .json()
+        resp.json()
             .await
-            .map_err(|_| DownloadError::InvalidResponse)?;
-        Ok(manifest)
+            .map_err(|_| DownloadError::InvalidResponse)
     }
 
     pub async fn pull_layer<F, P>(
@@ -172,6 +427,37 @@ impl OciClient {
         F: Fn(u64, u64) + Send + 'static,
     {
         let output_path = output_path.as_ref();
+        let mut attempt = 1;
+        loop {
+            match self
+                .try_pull_layer(layer, output_path, &progress_callback)
+                .await
+            {
+                Err(err) => {
+                    let Some(delay) = self
+                        .retry
+                        .as_ref()
+                        .and_then(|policy| policy.next_delay(attempt, &err))
+                    else {
+                        return Err(err);
+                    };
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn try_pull_layer<F>(
+        &self,
+        layer: &OciLayer,
+        output_path: &Path,
+        progress_callback: &F,
+    ) -> Result<u64, DownloadError>
+    where
+        F: Fn(u64, u64) + Send + 'static,
+    {
         let (part_path, meta_path) = ResumeSupport::get_part_paths(output_path);
         let (mut etag, mut last_modified) = ResumeSupport::read_metadata(&meta_path).await?;
 
@@ -183,27 +469,13 @@ impl OciClient {
         };
 
         loop {
-            let blob_url = format!(
-                "{}/{}/blobs/{}",
-                self.api
-                    .clone()
-                    .unwrap_or("https://ghcr.io/v2".to_string())
-                    .trim_end_matches('/'),
-                self.reference.package,
-                layer.digest
-            );
+            let blob_url = self.blob_url(&layer.digest);
 
             let mut headers = self.headers();
 
             ResumeSupport::prepare_resume_headers(&mut headers, downloaded, &etag, &last_modified);
 
-            let response = self
-                .client
-                .get(&blob_url)
-                .headers(headers.clone())
-                .send()
-                .await
-                .map_err(|err| DownloadError::NetworkError { source: err })?;
+            let response = self.send(&blob_url, headers).await?;
 
             let status = response.status();
 
@@ -239,6 +511,7 @@ impl OciClient {
                 return Err(DownloadError::ResourceError {
                     status,
                     url: blob_url,
+                    retry_after: retry_after(response.headers()),
                 });
             }
 
@@ -280,6 +553,15 @@ impl OciClient {
 
             ResumeSupport::write_metadata(&meta_path, remote_etag, remote_modified).await?;
 
+            // digests with an unsupported algorithm are not verified
+            let checksum = Checksum::parse(&layer.digest);
+            let mut hasher = checksum.as_ref().map(Checksum::hasher);
+            if let Some(ref mut hasher) = hasher {
+                if downloaded > 0 {
+                    hasher.update_from_file(&part_path).await?;
+                }
+            }
+
             let mut stream = response.bytes_stream();
             while let Some(chunk) = stream
                 .try_next()
@@ -287,11 +569,21 @@ impl OciClient {
                 .map_err(|_| DownloadError::ChunkError)?
             {
                 let chunk_size = chunk.len() as u64;
+                if let Some(ref mut hasher) = hasher {
+                    hasher.update(&chunk);
+                }
                 file.write_all(&chunk).await?;
 
                 downloaded += chunk_size;
                 progress_callback(chunk_size, 0);
             }
+            file.flush().await?;
+
+            if let Err(err) = verify_layer(layer, downloaded, checksum.zip(hasher)) {
+                fs::remove_file(&part_path).await.ok();
+                fs::remove_file(&meta_path).await.ok();
+                return Err(err);
+            }
 
             fs::rename(&part_path, &output_path).await?;
             fs::remove_file(&meta_path).await.ok();
@@ -305,6 +597,127 @@ impl OciClient {
     }
 }
 
+/// Checks a pulled layer against the size and digest from its manifest.
+fn verify_layer(
+    layer: &OciLayer,
+    downloaded: u64,
+    digest: Option<(Checksum, ChecksumHasher)>,
+) -> Result<(), DownloadError> {
+    if layer.size > 0 && downloaded != layer.size {
+        return Err(DownloadError::SizeMismatch {
+            expected: layer.size,
+            actual: downloaded,
+        });
+    }
+    match digest {
+        Some((checksum, hasher)) => checksum.verify(&hasher.finalize()),
+        None => Ok(()),
+    }
+}
+
+fn is_index(value: &Value) -> bool {
+    match value.get("mediaType").and_then(Value::as_str) {
+        Some(media_type) => {
+            media_type == "application/vnd.oci.image.index.v1+json"
+                || media_type == "application/vnd.docker.distribution.manifest.list.v2+json"
+        }
+        None => value.get("manifests").is_some() && value.get("layers").is_none(),
+    }
+}
+
+impl OciPlatform {
+    /// Parses `os/arch[/variant]`, e.g. `linux/arm64/v8`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.trim().splitn(3, '/');
+        let os = parts.next().filter(|s| !s.is_empty())?;
+        let architecture = parts.next().filter(|s| !s.is_empty())?;
+        let variant = parts.next().filter(|s| !s.is_empty());
+        Some(Self {
+            os: oci_os(os).to_string(),
+            architecture: architecture.to_string(),
+            variant: variant.map(String::from),
+        })
+    }
+
+    pub fn host() -> Self {
+        let architecture = match std::env::consts::ARCH {
+            "x86_64" => "amd64",
+            "aarch64" => "arm64",
+            "x86" => "386",
+            "powerpc64" if cfg!(target_endian = "little") => "ppc64le",
+            "powerpc64" => "ppc64",
+            "loongarch64" => "loong64",
+            arch => arch,
+        };
+        let variant = match std::env::consts::ARCH {
+            "arm" => Some("v7".to_string()),
+            _ => None,
+        };
+        Self {
+            os: oci_os(std::env::consts::OS).to_string(),
+            architecture: architecture.to_string(),
+            variant,
+        }
+    }
+
+    /// Whether `other` can run where this platform is requested. Entries without a
+    /// variant are taken to be the default variant of their architecture, e.g. `v8` for
+    /// `arm64`.
+    pub fn matches(&self, other: &OciPlatform) -> bool {
+        self.os == other.os
+            && self.architecture == other.architecture
+            && (self.variant.is_none()
+                || self.variant == other.variant
+                || (other.variant.is_none()
+                    && self.variant.as_deref() == default_variant(&self.architecture)))
+    }
+}
+
+/// OS name used in image indexes, which follow Go's `GOOS` rather than Rust's names.
+fn oci_os(os: &str) -> &str {
+    match os {
+        "macos" | "osx" => "darwin",
+        os => os,
+    }
+}
+
+fn default_variant(architecture: &str) -> Option<&'static str> {
+    match architecture {
+        "arm64" => Some("v8"),
+        "arm" => Some("v7"),
+        _ => None,
+    }
+}
+
+impl Display for OciPlatform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.os, self.architecture)?;
+        if let Some(ref variant) = self.variant {
+            write!(f, "/{}", variant)?;
+        }
+        Ok(())
+    }
+}
+
+impl OciIndex {
+    pub fn select(&self, platform: &OciPlatform) -> Option<&OciIndexEntry> {
+        let candidates = self
+            .manifests
+            .iter()
+            .filter(|m| m.platform.as_ref().is_some_and(|p| platform.matches(p)));
+
+        // prefer an exact variant match, e.g. arm64/v8 over arm64
+        candidates
+            .clone()
+            .find(|m| {
+                m.platform
+                    .as_ref()
+                    .is_some_and(|p| p.variant == platform.variant)
+            })
+            .or_else(|| candidates.clone().next())
+    }
+}
+
 impl OciLayer {
     pub fn get_title(&self) -> Option<String> {
         self.annotations
@@ -319,3 +732,167 @@ impl OciLayer {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(reference: &str) -> (String, String, String) {
+        let reference = Reference::from(reference);
+        (reference.registry, reference.package, reference.tag)
+    }
+
+    fn owned(registry: &str, package: &str, tag: &str) -> (String, String, String) {
+        (registry.to_string(), package.to_string(), tag.to_string())
+    }
+
+    #[test]
+    fn reference_docker_hub() {
+        assert_eq!(
+            parts("alpine"),
+            owned("docker.io", "library/alpine", "latest")
+        );
+        assert_eq!(
+            parts("alpine:3.20"),
+            owned("docker.io", "library/alpine", "3.20")
+        );
+        assert_eq!(parts("org/app:v1"), owned("docker.io", "org/app", "v1"));
+        assert_eq!(
+            parts("index.docker.io/org/app"),
+            owned("docker.io", "org/app", "latest")
+        );
+    }
+
+    #[test]
+    fn reference_registry() {
+        assert_eq!(
+            parts("ghcr.io/pkgforge/pkgcache/86box:v4.2.1-x86_64-linux"),
+            owned("ghcr.io", "pkgforge/pkgcache/86box", "v4.2.1-x86_64-linux")
+        );
+        assert_eq!(
+            parts("oci://localhost:5000/org/package:v1.0"),
+            owned("localhost:5000", "org/package", "v1.0")
+        );
+        assert_eq!(
+            parts("localhost/org/package"),
+            owned("localhost", "org/package", "latest")
+        );
+    }
+
+    #[test]
+    fn reference_digest() {
+        assert_eq!(
+            parts("ghcr.io/org/app@sha256:abc"),
+            owned("ghcr.io", "org/app", "sha256:abc")
+        );
+        // the digest wins over the tag
+        assert_eq!(
+            parts("quay.io/org/app:v1@sha256:abc"),
+            owned("quay.io", "org/app", "sha256:abc")
+        );
+    }
+
+    fn platform(value: &str) -> OciPlatform {
+        OciPlatform::parse(value).unwrap()
+    }
+
+    #[test]
+    fn platform_parse() {
+        let arm = platform("linux/arm64/v8");
+        assert_eq!(arm.os, "linux");
+        assert_eq!(arm.architecture, "arm64");
+        assert_eq!(arm.variant.as_deref(), Some("v8"));
+        assert_eq!(platform("linux/amd64/").variant, None);
+        assert_eq!(platform("linux/amd64").to_string(), "linux/amd64");
+        assert_eq!(platform("macos/arm64").os, "darwin");
+        assert!(platform("macos/arm64").matches(&platform("darwin/arm64")));
+        assert!(OciPlatform::parse("linux").is_none());
+        assert!(OciPlatform::parse("/amd64").is_none());
+    }
+
+    #[test]
+    fn platform_matches() {
+        assert!(platform("linux/amd64").matches(&platform("linux/amd64")));
+        assert!(!platform("linux/amd64").matches(&platform("linux/arm64")));
+        assert!(!platform("linux/amd64").matches(&platform("windows/amd64")));
+
+        // no variant requested takes any
+        assert!(platform("linux/arm").matches(&platform("linux/arm/v6")));
+        assert!(platform("linux/arm64/v8").matches(&platform("linux/arm64/v8")));
+        assert!(!platform("linux/arm/v7").matches(&platform("linux/arm/v6")));
+
+        // entries without a variant are the default one
+        assert!(platform("linux/arm64/v8").matches(&platform("linux/arm64")));
+        assert!(platform("linux/arm/v7").matches(&platform("linux/arm")));
+        assert!(!platform("linux/arm/v6").matches(&platform("linux/arm")));
+    }
+
+    #[test]
+    fn platform_host() {
+        let host = OciPlatform::host();
+        assert_eq!(host.os, oci_os(std::env::consts::OS));
+        assert_ne!(host.os, "macos");
+        assert!(!host.architecture.is_empty());
+        if cfg!(target_arch = "x86_64") {
+            assert_eq!(host.architecture, "amd64");
+        }
+    }
+
+    #[test]
+    fn reference_api_base() {
+        assert_eq!(
+            Reference::from("alpine").api_base(),
+            "https://registry-1.docker.io/v2"
+        );
+        assert_eq!(
+            Reference::from("localhost:5000/org/app").api_base(),
+            "http://localhost:5000/v2"
+        );
+        assert_eq!(
+            Reference::from("quay.io/org/app").api_base(),
+            "https://quay.io/v2"
+        );
+    }
+
+    fn layer(digest: &str, size: u64) -> OciLayer {
+        OciLayer {
+            media_type: "application/octet-stream".to_string(),
+            digest: digest.to_string(),
+            size,
+            annotations: HashMap::new(),
+        }
+    }
+
+    fn pulled(layer: &OciLayer, data: &[u8]) -> Result<(), DownloadError> {
+        let digest = Checksum::parse(&layer.digest).map(|checksum| {
+            let mut hasher = checksum.hasher();
+            hasher.update(data);
+            (checksum, hasher)
+        });
+        verify_layer(layer, data.len() as u64, digest)
+    }
+
+    #[test]
+    fn verify_layer_digest_and_size() {
+        let hello = "sha256:5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+        assert!(pulled(&layer(hello, 6), b"hello\n").is_ok());
+        assert!(matches!(
+            pulled(&layer(hello, 6), b"hallo\n"),
+            Err(DownloadError::ChecksumMismatch { .. })
+        ));
+        assert!(matches!(
+            pulled(&layer(hello, 7), b"hello\n"),
+            Err(DownloadError::SizeMismatch {
+                expected: 7,
+                actual: 6
+            })
+        ));
+        // unknown sizes and unsupported algorithms are not checked
+        assert!(pulled(&layer(hello, 0), b"hello\n").is_ok());
+        assert!(pulled(
+            &layer("md5:b1946ac92492d2347c6235b4d2611184", 6),
+            b"hallo\n"
+        )
+        .is_ok());
+    }
+}
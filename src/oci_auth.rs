@@ -0,0 +1,298 @@
+use std::{collections::HashMap, env, path::PathBuf};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::Deserialize;
+
+use url::Url;
+
+use crate::{error::DownloadError, platform::host_token_var, retry::retry_after};
+
+#[derive(Clone, Debug)]
+pub struct RegistryCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug)]
+pub struct AuthChallenge {
+    pub scheme: String,
+    pub params: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DockerConfig {
+    #[serde(default)]
+    auths: HashMap<String, DockerAuth>,
+}
+
+#[derive(Deserialize)]
+struct DockerAuth {
+    auth: Option<String>,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl RegistryCredentials {
+    /// Looks up credentials for `registry` from the environment, then the docker config
+    /// file.
+    pub fn resolve(registry: &str) -> Option<Self> {
+        Self::from_env(registry).or_else(|| Self::from_docker_config(registry))
+    }
+
+    /// Reads `OCI_USERNAME_<HOST>`/`OCI_PASSWORD_<HOST>` (e.g. `OCI_USERNAME_QUAY_IO`),
+    /// then `OCI_USERNAME`/`OCI_PASSWORD` if `OCI_REGISTRY` names `registry`, then
+    /// `GITHUB_TOKEN` for ghcr.io.
+    pub fn from_env(registry: &str) -> Option<Self> {
+        if let (Ok(username), Ok(password)) = (
+            env::var(host_token_var("OCI_USERNAME", registry)),
+            env::var(host_token_var("OCI_PASSWORD", registry)),
+        ) {
+            return Some(Self { username, password });
+        }
+
+        let scoped =
+            env::var("OCI_REGISTRY").is_ok_and(|value| value.trim().eq_ignore_ascii_case(registry));
+        if scoped {
+            if let (Ok(username), Ok(password)) =
+                (env::var("OCI_USERNAME"), env::var("OCI_PASSWORD"))
+            {
+                return Some(Self { username, password });
+            }
+        }
+
+        if registry == "ghcr.io" {
+            if let Ok(token) = env::var("GITHUB_TOKEN") {
+                return Some(Self {
+                    username: "token".to_string(),
+                    password: token,
+                });
+            }
+        }
+
+        None
+    }
+
+    pub fn from_docker_config(registry: &str) -> Option<Self> {
+        let path = env::var("DOCKER_CONFIG")
+            .map(PathBuf::from)
+            .or_else(|_| env::var("HOME").map(|home| PathBuf::from(home).join(".docker")))
+            .ok()?
+            .join("config.json");
+        let content = std::fs::read_to_string(path).ok()?;
+        let config: DockerConfig = serde_json::from_str(&content).ok()?;
+
+        let is_docker_hub = matches!(
+            registry,
+            "docker.io" | "index.docker.io" | "registry-1.docker.io"
+        );
+        let auth = config.auths.iter().find_map(|(key, auth)| {
+            let host = key
+                .trim_start_matches("https://")
+                .trim_start_matches("http://")
+                .split('/')
+                .next()
+                .unwrap_or_default();
+            let matches = host == registry
+                || (is_docker_hub && matches!(host, "docker.io" | "index.docker.io"));
+            matches.then_some(auth)
+        })?;
+
+        if let (Some(username), Some(password)) = (&auth.username, &auth.password) {
+            return Some(Self {
+                username: username.clone(),
+                password: password.clone(),
+            });
+        }
+
+        let decoded = STANDARD.decode(auth.auth.as_ref()?).ok()?;
+        let decoded = String::from_utf8(decoded).ok()?;
+        let (username, password) = decoded.split_once(':')?;
+        Some(Self {
+            username: username.to_string(),
+            password: password.to_string(),
+        })
+    }
+
+    pub fn basic_auth(&self) -> String {
+        format!(
+            "Basic {}",
+            STANDARD.encode(format!("{}:{}", self.username, self.password))
+        )
+    }
+}
+
+impl AuthChallenge {
+    /// Parses a `WWW-Authenticate` header, e.g.
+    /// `Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:foo:pull"`.
+    pub fn parse(header: &str) -> Option<Self> {
+        let (scheme, rest) = header.trim().split_once(' ').unwrap_or((header.trim(), ""));
+        let mut params = HashMap::new();
+        let mut chars = rest.chars().peekable();
+
+        loop {
+            while chars.next_if(|c| *c == ',' || c.is_whitespace()).is_some() {}
+
+            let key: String = std::iter::from_fn(|| chars.next_if(|c| *c != '=')).collect();
+            if key.is_empty() || chars.next().is_none() {
+                break;
+            }
+
+            let value: String = if chars.next_if_eq(&'"').is_some() {
+                let value = std::iter::from_fn(|| chars.next_if(|c| *c != '"')).collect();
+                chars.next();
+                value
+            } else {
+                std::iter::from_fn(|| chars.next_if(|c| *c != ',')).collect()
+            };
+
+            params.insert(key.trim().to_lowercase(), value.trim().to_string());
+        }
+
+        Some(Self {
+            scheme: scheme.to_lowercase(),
+            params,
+        })
+    }
+
+    /// Whether credentials for `registry` may be sent to the token realm. Only realms on
+    /// the registry host (or Docker Hub's auth server) are trusted, other hosts have to
+    /// be listed in `OCI_TRUSTED_REALMS`.
+    pub fn is_realm_trusted(&self, registry: &str) -> bool {
+        let Some(realm) = self
+            .params
+            .get("realm")
+            .and_then(|realm| Url::parse(realm).ok())
+        else {
+            return false;
+        };
+        let Some(host) = realm.host_str() else {
+            return false;
+        };
+        let host = match realm.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+
+        if host.eq_ignore_ascii_case(registry) {
+            return true;
+        }
+        let is_docker_hub = matches!(
+            registry,
+            "docker.io" | "index.docker.io" | "registry-1.docker.io"
+        );
+        if is_docker_hub && host == "auth.docker.io" {
+            return true;
+        }
+
+        env::var("OCI_TRUSTED_REALMS").is_ok_and(|realms| {
+            realms
+                .split(',')
+                .any(|realm| realm.trim().eq_ignore_ascii_case(&host))
+        })
+    }
+
+    /// Fetches a bearer token from the challenge realm.
+    pub async fn fetch_token(
+        &self,
+        client: &reqwest::Client,
+        default_scope: &str,
+        credentials: Option<&RegistryCredentials>,
+    ) -> Result<String, DownloadError> {
+        let Some(realm) = self.params.get("realm") else {
+            return Err(DownloadError::InvalidResponse);
+        };
+
+        let mut query = Vec::new();
+        if let Some(service) = self.params.get("service") {
+            query.push(("service", service.as_str()));
+        }
+        query.push((
+            "scope",
+            self.params
+                .get("scope")
+                .map(String::as_str)
+                .unwrap_or(default_scope),
+        ));
+
+        let mut request = client.get(realm).query(&query);
+        if let Some(credentials) = credentials {
+            request = request.basic_auth(&credentials.username, Some(&credentials.password));
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|err| DownloadError::NetworkError { source: err })?;
+
+        if !response.status().is_success() {
+            return Err(DownloadError::ResourceError {
+                url: realm.clone(),
+                status: response.status(),
+                retry_after: retry_after(response.headers()),
+            });
+        }
+
+        let token: TokenResponse = response
+            .json()
+            .await
+            .map_err(|_| DownloadError::InvalidResponse)?;
+
+        token
+            .token
+            .or(token.access_token)
+            .ok_or(DownloadError::InvalidResponse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bearer() {
+        let challenge = AuthChallenge::parse(
+            r#"Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:org/app:pull""#,
+        )
+        .unwrap();
+        assert_eq!(challenge.scheme, "bearer");
+        assert_eq!(challenge.params["realm"], "https://ghcr.io/token");
+        assert_eq!(challenge.params["service"], "ghcr.io");
+        assert_eq!(challenge.params["scope"], "repository:org/app:pull");
+    }
+
+    #[test]
+    fn parse_unquoted_and_spaced() {
+        let challenge =
+            AuthChallenge::parse(r#"Bearer Realm=https://auth.example/token, scope="a,b""#)
+                .unwrap();
+        assert_eq!(challenge.params["realm"], "https://auth.example/token");
+        assert_eq!(challenge.params["scope"], "a,b");
+    }
+
+    #[test]
+    fn parse_scheme_only() {
+        let challenge = AuthChallenge::parse("Basic").unwrap();
+        assert_eq!(challenge.scheme, "basic");
+        assert!(challenge.params.is_empty());
+    }
+
+    #[test]
+    fn realm_trust() {
+        let challenge =
+            |realm: &str| AuthChallenge::parse(&format!(r#"Bearer realm="{}""#, realm)).unwrap();
+        assert!(challenge("https://ghcr.io/token").is_realm_trusted("ghcr.io"));
+        assert!(challenge("http://localhost:5000/token").is_realm_trusted("localhost:5000"));
+        assert!(challenge("https://auth.docker.io/token").is_realm_trusted("docker.io"));
+        assert!(!challenge("https://auth.docker.io/token").is_realm_trusted("quay.io"));
+        assert!(!challenge("http://localhost:5001/token").is_realm_trusted("localhost:5000"));
+        assert!(!AuthChallenge::parse("Bearer")
+            .unwrap()
+            .is_realm_trusted("ghcr.io"));
+    }
+}
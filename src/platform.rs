@@ -1,29 +1,75 @@
 use std::{
+    cmp::Reverse,
     env,
+    fmt::Display,
+    path::Path,
     sync::{Arc, LazyLock},
 };
 
 use regex::Regex;
-use reqwest::header::{HeaderMap, AUTHORIZATION};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, LINK},
+    StatusCode,
+};
 use serde::Deserialize;
 use serde_json::Value;
+use tokio::time::sleep;
 use url::Url;
 
 use crate::{
-    downloader::{DownloadOptions, DownloadState, Downloader},
+    checksum::Checksum,
+    downloader::{DownloadOptions, DownloadPlan, DownloadState, Downloader},
     error::{DownloadError, PlatformError},
-    utils::{decode_uri, matches_pattern, should_fallback, FileMode},
+    oci::is_registry_host,
+    retry::{retry_after, RetryPolicy},
+    signature::{DetachedSignature, VerifyKey},
+    utils::{decode_uri, matches_pattern, should_fallback, FileMode, PromptCallback},
+    version::{alternate_tag, TagConstraint},
 };
 
+#[derive(Clone, Copy)]
 pub enum ApiType {
     PkgForge,
     Primary,
 }
 
+/// Which API release metadata is fetched from. Self-hosted instances always use their
+/// own API.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ApiPreference {
+    /// The pkgforge mirror, falling back to the primary API on errors or rate limits
+    #[default]
+    PkgforgeFirst,
+    /// Only the primary API, the project is never sent to a mirror
+    PrimaryOnly,
+    /// Only the pkgforge mirror
+    PkgforgeOnly,
+    /// Another mirror of the primary API of a platform, falling back to the primary API
+    /// like `PkgforgeFirst`. Other platforms are fetched as with `PkgforgeFirst`
+    Mirror(ForgeKind, String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForgeKind {
+    Github,
+    Gitlab,
+    Gitea,
+}
+
+/// A self-hosted instance, e.g. GitHub Enterprise Server or an on-prem GitLab.
+#[derive(Clone, Debug)]
+pub struct ForgeHost {
+    pub kind: ForgeKind,
+    /// e.g. `https://git.corp.example`
+    pub url: String,
+}
+
 #[derive(Debug)]
 pub enum PlatformUrl {
     Github(String),
     Gitlab(String),
+    /// `host/owner/repo[@tag]`
+    Gitea(String),
     Oci(String),
     DirectUrl(String),
 }
@@ -34,15 +80,160 @@ static GITHUB_RELEASE_RE: LazyLock<Regex> = LazyLock::new(|| {
     )
     .unwrap()
 });
+static GITEA_RELEASE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?i)(?:(?:https?://)?(codeberg\.org)/|gitea:((?:https?://)?[^/@\s]+)/)([^/@]+/[^/@]+)(?:@([^/\s]+(?:/[^/\s]*)*)?)?$",
+    )
+    .unwrap()
+});
 static GITLAB_RELEASE_RE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r"^(?i)(?:https?://)?(?:gitlab(?:\.com)?[:/])((?:\d+)|(?:[^/@]+(?:/[^/@]+)*))(?:@([^/\s]+(?:/[^/\s]*)*)?)?$")
         .unwrap()
 });
 
+impl ApiPreference {
+    /// Parses `pkgforge-first`, `primary-only`, `pkgforge-only` or `<platform>=<url>` for
+    /// a mirror, e.g. `github=https://gh-mirror.example.com`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "pkgforge-first" | "pkgforge" => Some(ApiPreference::PkgforgeFirst),
+            "primary-only" | "primary" => Some(ApiPreference::PrimaryOnly),
+            "pkgforge-only" => Some(ApiPreference::PkgforgeOnly),
+            mirror => {
+                let (kind, url) = mirror.split_once('=')?;
+                let kind = ForgeKind::parse(kind)?;
+                let parsed = Url::parse(url).ok()?;
+                (matches!(parsed.scheme(), "http" | "https") && parsed.host().is_some())
+                    .then(|| ApiPreference::Mirror(kind, url.trim_end_matches('/').to_string()))
+            }
+        }
+    }
+}
+
+impl ForgeKind {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "github" => Some(ForgeKind::Github),
+            "gitlab" => Some(ForgeKind::Gitlab),
+            "gitea" | "forgejo" => Some(ForgeKind::Gitea),
+            _ => None,
+        }
+    }
+}
+
+impl ForgeHost {
+    /// Parses `github:<host>`, `gitlab:<host>` or `gitea:<host>`. The host may include
+    /// a scheme and port, https is used if no scheme is given.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (kind, host) = value.trim().split_once(':')?;
+        let kind = ForgeKind::parse(kind)?;
+        let host = host.trim_end_matches('/');
+        let url = if host.contains("://") {
+            host.to_string()
+        } else {
+            format!("https://{}", host)
+        };
+        Url::parse(&url).ok()?.host_str()?;
+        Some(Self { kind, url })
+    }
+
+    /// Host (and port) without the scheme.
+    pub fn host(&self) -> &str {
+        self.url
+            .split_once("://")
+            .map_or(self.url.as_str(), |(_, host)| host)
+    }
+}
+
+/// Splits a project qualified with the URL of its instance, e.g.
+/// `https://git.corp.example/group/project@v1`, into the instance URL and
+/// `group/project@v1`. Projects qualified with just the host are split only if the host
+/// is one of `hosts`.
+pub fn split_instance<'a>(project: &'a str, hosts: &[ForgeHost]) -> (Option<String>, &'a str) {
+    let project = project.trim();
+    if let Some((scheme, rest)) = project.split_once("://") {
+        if let Some((host, project)) = rest.split_once('/') {
+            return (Some(format!("{}://{}", scheme, host)), project);
+        }
+    }
+
+    if let Some((host, rest)) = project.split_once('/') {
+        if let Some(forge) = hosts.iter().find(|h| h.host().eq_ignore_ascii_case(host)) {
+            return (Some(forge.url.clone()), rest);
+        }
+    }
+
+    (None, project)
+}
+
+/// Name of the environment variable holding the token for `host`, e.g.
+/// `GITLAB_TOKEN_GIT_CORP_EXAMPLE` for `git.corp.example`.
+pub fn host_token_var(token_env_var: &str, host: &str) -> String {
+    let host = host
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("{}_{}", token_env_var, host)
+}
+
 impl PlatformUrl {
+    /// Like [`PlatformUrl::parse`], but also recognizes projects on the self-hosted
+    /// instances in `hosts`. Their project includes the instance URL.
+    pub fn parse_with_hosts(
+        url: impl Into<String>,
+        hosts: &[ForgeHost],
+    ) -> Result<Self, PlatformError> {
+        let url = url.into();
+        if let Some(platform_url) = Self::parse_instance(&url, hosts) {
+            return Ok(platform_url);
+        }
+        Self::parse(url)
+    }
+
+    fn parse_instance(url: &str, hosts: &[ForgeHost]) -> Option<Self> {
+        let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let (host, path) = rest.split_once('/')?;
+        let forge = hosts.iter().find(|h| h.host().eq_ignore_ascii_case(host))?;
+
+        let (path, tag) = match path.split_once('@') {
+            Some((path, tag)) => (path, Some(tag)),
+            None => (path, None),
+        };
+        let path = path.trim_end_matches('/');
+        let segments = path.split('/').filter(|s| !s.is_empty()).count();
+        let is_project = match forge.kind {
+            // if it's API url or contains `/-/` in path, ignore it
+            ForgeKind::Gitlab => segments >= 2 && !path.starts_with("api") && !path.contains("/-/"),
+            ForgeKind::Github | ForgeKind::Gitea => segments == 2,
+        };
+        if !is_project {
+            return Some(PlatformUrl::DirectUrl(url.to_string()));
+        }
+
+        let tag = tag
+            .map(|tag| tag.trim_matches(&['\'', '"', ' '][..]))
+            .filter(|&tag| !tag.is_empty())
+            .map(decode_uri);
+        let project = match tag {
+            Some(tag) => format!("{}/{}@{}", forge.url, path, tag),
+            None => format!("{}/{}", forge.url, path),
+        };
+        Some(match forge.kind {
+            ForgeKind::Github => PlatformUrl::Github(project),
+            ForgeKind::Gitlab => PlatformUrl::Gitlab(project),
+            ForgeKind::Gitea => PlatformUrl::Gitea(project),
+        })
+    }
+
     pub fn parse(url: impl Into<String>) -> Result<Self, PlatformError> {
         let url = url.into();
-        if url.starts_with("ghcr.io") {
+        if url.starts_with("oci://") || url.starts_with("ghcr.io") {
             return Ok(PlatformUrl::Oci(url));
         }
         if GITHUB_RELEASE_RE.is_match(&url) {
@@ -62,6 +253,19 @@ impl PlatformUrl {
 
             return Err(PlatformError::InvalidInput(url));
         }
+        if let Some(caps) = GITEA_RELEASE_RE.captures(&url) {
+            let host = caps.get(1).or(caps.get(2)).unwrap().as_str();
+            let project = caps.get(3).unwrap().as_str();
+            let tag = caps
+                .get(4)
+                .map(|tag| tag.as_str().trim_matches(&['\'', '"', ' '][..]))
+                .filter(|&tag| !tag.is_empty())
+                .map(decode_uri);
+            return Ok(PlatformUrl::Gitea(match tag {
+                Some(tag) => format!("{}/{}@{}", host, project, tag),
+                None => format!("{}/{}", host, project),
+            }));
+        }
         if GITLAB_RELEASE_RE.is_match(&url) {
             if let Some(caps) = GITLAB_RELEASE_RE.captures(&url) {
                 let project = caps.get(1).unwrap().as_str();
@@ -87,11 +291,98 @@ impl PlatformUrl {
             Ok(parsed) if !parsed.scheme().is_empty() && parsed.host().is_some() => {
                 Ok(PlatformUrl::DirectUrl(parsed.to_string()))
             }
+            // registry[:port]/namespace/repo[:tag][@digest]
+            _ if url
+                .split_once('/')
+                .is_some_and(|(host, _)| is_registry_host(host)) =>
+            {
+                Ok(PlatformUrl::Oci(url))
+            }
             _ => Err(PlatformError::InvalidInput(url)),
         }
     }
 }
 
+/// Something downloaded straight from a repository rather than from a release.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RepoSource {
+    /// Source archive of the ref
+    Archive(ArchiveFormat),
+    /// A single file at the ref
+    File(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    TarGz,
+    Zip,
+}
+
+impl RepoSource {
+    /// Splits the source off `owner/repo[@ref]#source` (or `#tarball`, `#zipball`) and
+    /// `owner/repo@ref:path/to/file`, leaving `owner/repo[@ref]`. The path is taken as is,
+    /// even if it ends in one of the archive suffixes.
+    pub fn split(project: &str) -> (&str, Option<Self>) {
+        let project = project.trim();
+
+        // refs can't contain `:`
+        if let Some(at) = project.find('@') {
+            if let Some((git_ref, path)) = project[at + 1..].split_once(':') {
+                let path = path.trim_start_matches('/');
+                if !path.is_empty() {
+                    return (
+                        &project[..at + 1 + git_ref.len()],
+                        Some(RepoSource::File(path.to_string())),
+                    );
+                }
+            }
+        }
+
+        if let Some((rest, suffix)) = project.rsplit_once('#') {
+            let format = match suffix.to_lowercase().as_str() {
+                "source" | "tarball" => Some(ArchiveFormat::TarGz),
+                "zipball" => Some(ArchiveFormat::Zip),
+                _ => None,
+            };
+            if let Some(format) = format {
+                return (rest, Some(RepoSource::Archive(format)));
+            }
+        }
+
+        (project, None)
+    }
+
+    /// Name the file is saved as if no output file is given.
+    pub fn file_name(&self, repo: &str, git_ref: &str) -> String {
+        match self {
+            RepoSource::Archive(format) => {
+                let repo = repo.rsplit('/').next().unwrap_or(repo);
+                let git_ref = git_ref.replace('/', "-");
+                format!("{}-{}.{}", repo, git_ref, format.extension())
+            }
+            RepoSource::File(path) => path.rsplit('/').next().unwrap_or(path).to_string(),
+        }
+    }
+}
+
+impl ArchiveFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::Zip => "zip",
+        }
+    }
+}
+
+impl Display for RepoSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepoSource::Archive(format) => write!(f, "source archive ({})", format.extension()),
+            RepoSource::File(path) => write!(f, "{}", path),
+        }
+    }
+}
+
 pub trait DownloadableAsset {
     fn name(&self) -> &str;
     fn size(&self) -> u64;
@@ -100,17 +391,53 @@ pub trait DownloadableAsset {
 
 pub trait ReleasePlatform {
     const API_BASE_PRIMARY: &'static str;
-    const API_BASE_PKGFORGE: &'static str;
+    /// pkgforge mirror of the API, tried before the primary one
+    const API_BASE_PKGFORGE: Option<&'static str>;
     const TOKEN_ENV_VAR: &'static str;
+    /// Which platform this is, for mirrors given as [`ApiPreference::Mirror`]
+    const FORGE_KIND: Option<ForgeKind> = None;
 
     fn format_project_path(project: &str) -> Result<(String, String), PlatformError>;
     fn format_api_path(project: &str, tag: Option<&str>) -> Result<String, PlatformError>;
+    /// API path of `source` at `git_ref`, which may be a tag, branch or commit.
+    fn format_source_path(
+        project: &str,
+        git_ref: &str,
+        source: &RepoSource,
+    ) -> Result<String, PlatformError>;
+
+    /// `Accept` header needed to get `source` as is rather than e.g. JSON metadata.
+    fn source_accept(_source: &RepoSource) -> Option<&'static str> {
+        None
+    }
+
+    /// API base of a self-hosted instance at `instance_url`.
+    fn instance_api_base(instance_url: &str) -> String {
+        instance_url.trim_end_matches('/').to_string()
+    }
+
+    /// Splits `project` into the URL of the instance it names, if any, and the project
+    /// on that instance. See [`split_instance`].
+    fn split_instance<'a>(project: &'a str, hosts: &[ForgeHost]) -> (Option<String>, &'a str) {
+        split_instance(project, hosts)
+    }
 }
 
 pub trait ReleaseAsset {
     fn name(&self) -> &str;
     fn size(&self) -> Option<u64>;
     fn download_url(&self) -> &str;
+
+    /// Whether downloading needs the platform token, which is otherwise only sent to
+    /// the API.
+    fn requires_auth(&self) -> bool {
+        false
+    }
+
+    /// Name to save the asset as, if the download URL doesn't end with it.
+    fn file_name(&self) -> Option<String> {
+        None
+    }
 }
 
 pub trait Release<A: ReleaseAsset> {
@@ -121,6 +448,22 @@ pub trait Release<A: ReleaseAsset> {
     fn assets(&self) -> Vec<A>;
 }
 
+#[derive(Clone, Debug)]
+pub struct ReleaseInfo {
+    pub name: String,
+    pub tag_name: String,
+    pub prerelease: bool,
+    pub published_at: String,
+    pub assets: Vec<AssetInfo>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AssetInfo {
+    pub name: String,
+    pub size: Option<u64>,
+    pub download_url: String,
+}
+
 #[derive(Clone)]
 pub struct PlatformDownloadOptions {
     pub output_path: Option<String>,
@@ -134,55 +477,451 @@ pub struct PlatformDownloadOptions {
     pub extract_archive: bool,
     pub extract_dir: Option<String>,
     pub file_mode: FileMode,
-    pub prompt: Option<Arc<dyn Fn(&str) -> Result<bool, DownloadError> + Send + Sync + 'static>>,
+    pub prompt: Option<PromptCallback>,
+    pub checksum: Option<Checksum>,
+    /// Don't look up `checksum` in the checksum files published in the release
+    pub skip_checksum: bool,
+    pub segments: Option<u64>,
+    pub retry: Option<RetryPolicy>,
+    pub verify_key: Option<VerifyKey>,
+    /// Detached signature of the asset, looked up in the release if not set
+    pub signature: Option<Vec<u8>>,
+    pub dry_run: bool,
+    pub api: ApiPreference,
+    /// Rank assets for this target and drop the ones built for other platforms
+    pub auto: Option<AssetTarget>,
+}
+
+/// Platform that release assets are ranked against when selecting them automatically.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetTarget {
+    pub arch: String,
+    pub os: String,
+    pub libc: Option<String>,
+}
+
+const CHECKSUM_EXTENSIONS: [&str; 7] = [
+    "sha256",
+    "sha256sum",
+    "sha512",
+    "sha512sum",
+    "b3",
+    "b3sum",
+    "blake3",
+];
+
+const CHECKSUM_FILES: [&str; 7] = [
+    "checksums",
+    "sha256sums",
+    "sha512sums",
+    "b3sums",
+    "blake3sums",
+    "sha256sum",
+    "sha512sum",
+];
+
+const SIGNATURE_EXTENSIONS: [&str; 4] = [".sig", ".asc", ".minisig", ".pem"];
+
+/// Whether `name` is a checksum file or signature rather than an asset of its own.
+fn is_verification_file(name: &str) -> bool {
+    let name = name.to_lowercase();
+    let stem = name.trim_end_matches(".txt");
+    SIGNATURE_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+        || CHECKSUM_FILES.iter().any(|file| stem.ends_with(file))
+        || stem
+            .rsplit_once('.')
+            .is_some_and(|(_, ext)| CHECKSUM_EXTENSIONS.contains(&ext))
+}
+
+/// Finds the checksum file that should list `asset_name`. Checksum files and signatures
+/// have none, they aren't verified against another checksum file.
+pub fn find_checksum_asset<'a, A: ReleaseAsset>(
+    asset_name: &str,
+    assets: &'a [A],
+) -> Option<&'a A> {
+    if is_verification_file(asset_name) {
+        return None;
+    }
+
+    let sidecar = assets.iter().find(|asset| {
+        asset
+            .name()
+            .strip_prefix(asset_name)
+            .and_then(|ext| ext.strip_prefix('.'))
+            .is_some_and(|ext| {
+                CHECKSUM_EXTENSIONS.contains(&ext.trim_end_matches(".txt").to_lowercase().as_str())
+            })
+    });
+
+    sidecar.or_else(|| {
+        assets.iter().find(|asset| {
+            let name = asset.name().to_lowercase();
+            let stem = name.trim_end_matches(".txt");
+            asset.name() != asset_name
+                && !SIGNATURE_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+                && CHECKSUM_FILES.iter().any(|file| stem.ends_with(file))
+        })
+    })
+}
+
+// checked in order, so more specific names must come first
+const ARCH_ALIASES: [(&str, &[&str]); 8] = [
+    (
+        "x86_64",
+        &[
+            "x86_64", "x86-64", "amd64", "x64", "64bit", "win64", "linux64",
+        ],
+    ),
+    ("aarch64", &["aarch64", "arm64", "armv8"]),
+    (
+        "arm",
+        &[
+            "armv7", "armv7l", "armv7a", "armhf", "armv6", "armv6l", "armel", "arm",
+        ],
+    ),
+    (
+        "x86",
+        &["i386", "i586", "i686", "x86", "386", "32bit", "win32"],
+    ),
+    ("riscv64", &["riscv64", "riscv64gc"]),
+    ("powerpc64", &["ppc64le", "powerpc64le", "ppc64"]),
+    ("s390x", &["s390x"]),
+    ("loongarch64", &["loongarch64", "loong64"]),
+];
+
+const OS_ALIASES: [(&str, &[&str]); 7] = [
+    ("android", &["android"]),
+    ("linux", &["linux", "linux64", "appimage", "deb", "rpm"]),
+    ("macos", &["macos", "darwin", "osx", "apple", "mac", "dmg"]),
+    (
+        "windows",
+        &[
+            "windows", "win", "win32", "win64", "msvc", "mingw", "exe", "msi",
+        ],
+    ),
+    ("freebsd", &["freebsd"]),
+    ("netbsd", &["netbsd"]),
+    ("openbsd", &["openbsd"]),
+];
+
+const PACKAGE_EXTENSIONS: [&str; 9] = [
+    ".deb",
+    ".rpm",
+    ".apk",
+    ".pkg.tar.zst",
+    ".flatpak",
+    ".snap",
+    ".msi",
+    ".dmg",
+    ".pkg",
+];
+
+const AUXILIARY_EXTENSIONS: [&str; 10] = [
+    ".sig",
+    ".asc",
+    ".minisig",
+    ".pem",
+    ".cert",
+    ".crt",
+    ".sbom",
+    ".spdx",
+    ".intoto.jsonl",
+    ".pdb",
+];
+
+const AUXILIARY_TOKENS: [&str; 11] = [
+    "src",
+    "source",
+    "sources",
+    "vendor",
+    "vendored",
+    "debug",
+    "dbg",
+    "dbgsym",
+    "debuginfo",
+    "symbols",
+    "dsym",
+];
+
+impl AssetTarget {
+    pub fn host() -> Self {
+        let os = env::consts::OS;
+        let libc = (os == "linux").then(|| if is_musl_host() { "musl" } else { "gnu" });
+        Self {
+            arch: env::consts::ARCH.to_string(),
+            os: os.to_string(),
+            libc: libc.map(String::from),
+        }
+    }
+
+    /// Parses a target triple such as `x86_64-unknown-linux-musl`, `aarch64-apple-darwin`
+    /// or `x86_64-pc-windows-msvc`.
+    pub fn parse(triple: &str) -> Option<Self> {
+        let triple = triple.trim().to_lowercase();
+        let (arch, rest) = triple.split_once('-')?;
+        let arch = find_alias(arch, &ARCH_ALIASES)?;
+        let os = find_alias(rest, &OS_ALIASES)?;
+        let libc = asset_libc(rest).or((os == "linux").then_some("gnu"));
+        Some(Self {
+            arch: arch.to_string(),
+            os: os.to_string(),
+            libc: libc.map(String::from),
+        })
+    }
+
+    /// Scores how well an asset named `name` fits this target, higher is better.
+    /// Returns `None` if the asset is built for another OS, architecture or libc.
+    pub fn score(&self, name: &str) -> Option<i32> {
+        let name = name.to_lowercase();
+        let mut score = 0;
+
+        match find_alias(&name, &ARCH_ALIASES) {
+            Some(arch) if arch == self.arch => score += 4,
+            Some(_) => return None,
+            None if self.os == "macos"
+                && ["universal", "universal2"]
+                    .iter()
+                    .any(|t| has_token(&name, t)) =>
+            {
+                score += 3
+            }
+            None => {}
+        }
+
+        match find_alias(&name, &OS_ALIASES) {
+            Some(os) if os == self.os => score += 4,
+            Some(_) => return None,
+            None => {}
+        }
+
+        // musl binaries are usually static and run anywhere, glibc ones don't run on musl
+        match (asset_libc(&name), self.libc.as_deref()) {
+            (Some(libc), Some(host)) if libc == host => score += 2,
+            (Some("gnu"), Some("musl")) => return None,
+            _ => {}
+        }
+
+        if PACKAGE_EXTENSIONS.iter().any(|ext| name.ends_with(ext)) {
+            score -= 2;
+        }
+
+        if is_auxiliary(&name) {
+            score -= 20;
+        }
+
+        Some(score)
+    }
+}
+
+impl Display for AssetTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.arch, self.os)?;
+        if let Some(ref libc) = self.libc {
+            write!(f, "-{}", libc)?;
+        }
+        Ok(())
+    }
+}
+
+fn find_alias(name: &str, aliases: &[(&'static str, &[&str])]) -> Option<&'static str> {
+    aliases
+        .iter()
+        .find(|(_, names)| names.iter().any(|alias| has_token(name, alias)))
+        .map(|(canonical, _)| *canonical)
+}
+
+/// Whether `token` occurs in `name` without letters or digits on either side.
+fn has_token(name: &str, token: &str) -> bool {
+    name.match_indices(token).any(|(start, _)| {
+        let before = name[..start].chars().next_back();
+        let after = name[start + token.len()..].chars().next();
+        !before.is_some_and(|c| c.is_ascii_alphanumeric())
+            && !after.is_some_and(|c| c.is_ascii_alphanumeric())
+    })
+}
+
+fn asset_libc(name: &str) -> Option<&'static str> {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .find_map(|token| {
+            if token.starts_with("musl") {
+                Some("musl")
+            } else if token.starts_with("gnu") || token == "glibc" {
+                Some("gnu")
+            } else {
+                None
+            }
+        })
+}
+
+/// Checksums, signatures, source tarballs and debug symbols.
+fn is_auxiliary(name: &str) -> bool {
+    let stem = name.trim_end_matches(".txt");
+    AUXILIARY_EXTENSIONS.iter().any(|ext| name.ends_with(ext))
+        || CHECKSUM_EXTENSIONS
+            .iter()
+            .any(|ext| stem.rsplit_once('.').is_some_and(|(_, e)| e == *ext))
+        || CHECKSUM_FILES.iter().any(|file| stem.ends_with(file))
+        || AUXILIARY_TOKENS.iter().any(|token| has_token(name, token))
+}
+
+fn is_musl_host() -> bool {
+    let has_loader = |dir: &str, prefix: &str| {
+        std::fs::read_dir(dir).is_ok_and(|entries| {
+            entries
+                .flatten()
+                .any(|entry| entry.file_name().to_string_lossy().starts_with(prefix))
+        })
+    };
+    has_loader("/lib", "ld-musl-")
+        && !Path::new("/lib64").is_dir()
+        && !has_loader("/lib", "ld-linux")
+}
+
+pub const DEFAULT_MAX_PAGES: usize = 10;
+
+struct ReleasePage<R> {
+    releases: Vec<R>,
+    next: Option<NextPage>,
+}
+
+struct NextPage {
+    api_type: ApiType,
+    url: String,
 }
 
-#[derive(Default)]
 pub struct ReleaseHandler<'a, P: ReleasePlatform> {
     downloader: Downloader<'a>,
+    retry: Option<RetryPolicy>,
+    max_pages: usize,
+    base_url: Option<String>,
+    token: Option<String>,
     _platform: std::marker::PhantomData<P>,
 }
 
+impl<P: ReleasePlatform> Default for ReleaseHandler<'_, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<P: ReleasePlatform> ReleaseHandler<'_, P> {
     pub fn new() -> Self {
         Self {
             downloader: Downloader::default(),
+            retry: None,
+            max_pages: DEFAULT_MAX_PAGES,
+            base_url: None,
+            token: None,
             _platform: std::marker::PhantomData,
         }
     }
 
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Uses the API of another instance of the platform, e.g. a self-hosted one, instead
+    /// of the default. The pkgforge mirror is not used for it, and the token is read from
+    /// the variable named by [`host_token_var`] unless set with [`Self::with_token`].
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Uses the instance at `instance_url`, e.g. `https://github.corp.example`.
+    pub fn with_instance(self, instance_url: &str) -> Self {
+        self.with_base_url(P::instance_api_base(instance_url))
+    }
+
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Whether a token is set, either with [`Self::with_token`] or in [`Self::token_var`].
+    pub fn has_token(&self) -> bool {
+        self.token().is_some()
+    }
+
+    /// Name of the environment variable the token is read from.
+    pub fn token_var(&self) -> String {
+        let host = self
+            .base_url
+            .as_deref()
+            .filter(|base_url| *base_url != P::API_BASE_PRIMARY)
+            .and_then(|base_url| Url::parse(base_url).ok())
+            .and_then(|url| {
+                let host = url.host_str()?;
+                Some(match url.port() {
+                    Some(port) => format!("{}:{}", host, port),
+                    None => host.to_string(),
+                })
+            });
+
+        match host {
+            Some(host) => host_token_var(P::TOKEN_ENV_VAR, &host),
+            None => P::TOKEN_ENV_VAR.to_string(),
+        }
+    }
+
+    fn token(&self) -> Option<String> {
+        self.token
+            .clone()
+            .or_else(|| env::var(self.token_var()).ok())
+    }
+
+    /// Limits how many pages of releases are fetched while looking for a match.
+    pub fn with_max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = max_pages.max(1);
+        self
+    }
+
     async fn call_api(
         &self,
         api_type: &ApiType,
+        base_url: &str,
         project: &str,
         tag: Option<&str>,
     ) -> Result<reqwest::Response, PlatformError> {
-        let base_url = match api_type {
-            ApiType::PkgForge => P::API_BASE_PKGFORGE,
-            ApiType::Primary => P::API_BASE_PRIMARY,
-        };
-
         let api_path = P::format_api_path(project, tag)?;
         let url = format!("{}{}", base_url, api_path);
+        self.get(api_type, &url).await
+    }
 
+    fn auth_headers(&self) -> Result<HeaderMap, PlatformError> {
         let mut headers = HeaderMap::new();
-
-        if matches!(api_type, ApiType::Primary) {
-            if let Ok(token) = env::var(P::TOKEN_ENV_VAR) {
-                headers.insert(AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
-            }
+        if let Some(token) = self.token() {
+            let value =
+                HeaderValue::from_str(&format!("Bearer {}", token.trim())).map_err(|_| {
+                    PlatformError::InvalidInput(match self.token {
+                        Some(_) => "Token".to_string(),
+                        None => format!("Token in {}", self.token_var()),
+                    })
+                })?;
+            headers.insert(AUTHORIZATION, value);
         }
+        Ok(headers)
+    }
+
+    async fn get(&self, api_type: &ApiType, url: &str) -> Result<reqwest::Response, PlatformError> {
+        let headers = match api_type {
+            ApiType::Primary => self.auth_headers()?,
+            ApiType::PkgForge => HeaderMap::new(),
+        };
 
         Ok(self
             .downloader
             .client()
-            .get(&url)
+            .get(url)
             .headers(headers)
             .send()
             .await
             .map_err(|err| DownloadError::NetworkError { source: err })?)
     }
 
+    /// Fetches the first page of releases of `project`, or the release tagged `tag`.
+    /// See [`Self::fetch_matching_releases`] to look further than the first page.
     pub async fn fetch_releases<R>(
         &self,
         project: &str,
@@ -191,57 +930,250 @@ impl<P: ReleasePlatform> ReleaseHandler<'_, P> {
     where
         R: for<'de> Deserialize<'de>,
     {
-        let response = match self.call_api(&ApiType::PkgForge, project, tag).await {
-            Ok(resp) => {
-                let status = resp.status();
-                if should_fallback(status) {
-                    self.call_api(&ApiType::Primary, project, tag).await?
-                } else {
-                    resp
+        let page = self
+            .fetch_page::<R>(project, tag, &ApiPreference::default(), None)
+            .await?;
+        Ok(page.releases)
+    }
+
+    /// Fetches releases of `project` page by page, until one matching `options.tag` (or
+    /// a stable release if no tag is given) is found or the page limit is reached.
+    pub async fn fetch_matching_releases<R, A>(
+        &self,
+        project: &str,
+        options: &PlatformDownloadOptions,
+    ) -> Result<Vec<R>, PlatformError>
+    where
+        R: Release<A> + for<'de> Deserialize<'de>,
+        A: ReleaseAsset,
+    {
+        let tag = options.tag.as_deref();
+        let constraint = tag.map(TagConstraint::parse).transpose()?;
+        // version constraints are resolved against the full release list
+        let tag = tag.filter(|_| constraint.as_ref().is_some_and(|c| c.is_exact()));
+
+        let mut releases: Vec<R> = Vec::new();
+        let mut next = None;
+        for _ in 0..self.max_pages {
+            let page = match self
+                .fetch_page::<R>(project, tag, &options.api, next.as_ref())
+                .await
+            {
+                // `1.2.3` for a release tagged `v1.2.3`, or the other way around
+                Err(err) if is_not_found(&err) => {
+                    let Some(tag) = tag.and_then(alternate_tag) else {
+                        return Err(err);
+                    };
+                    match self
+                        .fetch_page::<R>(project, Some(&tag), &options.api, None)
+                        .await
+                    {
+                        Err(alt_err) if is_not_found(&alt_err) => return Err(err),
+                        page => page?,
+                    }
                 }
+                page => page?,
+            };
+            releases.extend(page.releases);
+
+            let found = match constraint {
+                Some(ref constraint) => constraint.select(&releases).is_some(),
+                None => releases.iter().any(|release| !release.is_prerelease()),
+            };
+            match page.next {
+                Some(page) if !found => next = Some(page),
+                _ => break,
             }
-            Err(err) => return Err(err),
+        }
+
+        Ok(releases)
+    }
+
+    async fn fetch_page<R>(
+        &self,
+        project: &str,
+        tag: Option<&str>,
+        api: &ApiPreference,
+        next: Option<&NextPage>,
+    ) -> Result<ReleasePage<R>, PlatformError>
+    where
+        R: for<'de> Deserialize<'de>,
+    {
+        let mut attempt = 1;
+        loop {
+            match self.try_fetch_page(project, tag, api, next).await {
+                Err(err) => {
+                    let Some(delay) = self
+                        .retry
+                        .as_ref()
+                        .and_then(|policy| policy.next_platform_delay(attempt, &err))
+                    else {
+                        return Err(err);
+                    };
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn try_fetch_page<R>(
+        &self,
+        project: &str,
+        tag: Option<&str>,
+        api: &ApiPreference,
+        next: Option<&NextPage>,
+    ) -> Result<ReleasePage<R>, PlatformError>
+    where
+        R: for<'de> Deserialize<'de>,
+    {
+        let (api_type, response) = match next {
+            Some(next) => (next.api_type, self.get(&next.api_type, &next.url).await?),
+            None => self.fetch_first_page(project, tag, api).await?,
         };
 
         if !response.status().is_success() {
             return Err(DownloadError::ResourceError {
                 url: response.url().to_string(),
                 status: response.status(),
+                retry_after: retry_after(response.headers()),
             }
             .into());
         }
 
+        let next =
+            next_page_url(response.url(), response.headers()).map(|url| NextPage { api_type, url });
+
         let value: Value = response
             .json()
             .await
             .map_err(|_| PlatformError::InvalidResponse)?;
 
-        match value {
+        let releases = match value {
             Value::Array(_) => {
-                serde_json::from_value(value).map_err(|_| PlatformError::InvalidResponse)
+                serde_json::from_value(value).map_err(|_| PlatformError::InvalidResponse)?
             }
             Value::Object(_) => {
                 let single: R =
                     serde_json::from_value(value).map_err(|_| PlatformError::InvalidResponse)?;
-                Ok(vec![single])
+                vec![single]
             }
-            _ => Err(PlatformError::InvalidResponse),
+            _ => return Err(PlatformError::InvalidResponse),
+        };
+
+        Ok(ReleasePage { releases, next })
+    }
+
+    async fn fetch_first_page(
+        &self,
+        project: &str,
+        tag: Option<&str>,
+        api: &ApiPreference,
+    ) -> Result<(ApiType, reqwest::Response), PlatformError> {
+        let primary = self.base_url.as_deref().unwrap_or(P::API_BASE_PRIMARY);
+        let mirror = match api {
+            _ if self.base_url.is_some() => None,
+            ApiPreference::PrimaryOnly => None,
+            ApiPreference::Mirror(kind, url) if P::FORGE_KIND == Some(*kind) => Some(url.as_str()),
+            ApiPreference::PkgforgeFirst
+            | ApiPreference::PkgforgeOnly
+            | ApiPreference::Mirror(..) => P::API_BASE_PKGFORGE,
+        };
+
+        let Some(mirror) = mirror else {
+            if *api == ApiPreference::PkgforgeOnly && self.base_url.is_none() {
+                return Err(PlatformError::InvalidInput(format!(
+                    "pkgforge-only API for {}, which has no pkgforge mirror,",
+                    P::API_BASE_PRIMARY
+                )));
+            }
+            let response = self
+                .call_api(&ApiType::Primary, primary, project, tag)
+                .await?;
+            return Ok((ApiType::Primary, response));
+        };
+
+        let result = self
+            .call_api(&ApiType::PkgForge, mirror, project, tag)
+            .await;
+        if *api == ApiPreference::PkgforgeOnly {
+            return Ok((ApiType::PkgForge, result?));
+        }
+
+        match result {
+            Ok(response) if !should_fallback(response.status()) => {
+                Ok((ApiType::PkgForge, response))
+            }
+            Ok(_) | Err(PlatformError::DownloadError(DownloadError::NetworkError { .. })) => {
+                let response = self
+                    .call_api(&ApiType::Primary, primary, project, tag)
+                    .await?;
+                Ok((ApiType::Primary, response))
+            }
+            Err(err) => Err(err),
         }
     }
 
-    pub async fn filter_releases<R, A>(
+    /// Fetches `path` from the primary API, for endpoints the mirror doesn't serve.
+    pub async fn fetch_json<T>(&self, path: &str) -> Result<T, PlatformError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        let mut attempt = 1;
+        loop {
+            match self.try_fetch_json(path).await {
+                Err(err) => {
+                    let Some(delay) = self
+                        .retry
+                        .as_ref()
+                        .and_then(|policy| policy.next_platform_delay(attempt, &err))
+                    else {
+                        return Err(err);
+                    };
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn try_fetch_json<T>(&self, path: &str) -> Result<T, PlatformError>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        let base_url = self.base_url.as_deref().unwrap_or(P::API_BASE_PRIMARY);
+        let response = self
+            .get(&ApiType::Primary, &format!("{}{}", base_url, path))
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(DownloadError::ResourceError {
+                url: response.url().to_string(),
+                status: response.status(),
+                retry_after: retry_after(response.headers()),
+            }
+            .into());
+        }
+
+        response
+            .json()
+            .await
+            .map_err(|_| PlatformError::InvalidResponse)
+    }
+
+    pub fn select_release<'r, R, A>(
         &self,
-        releases: &[R],
+        releases: &'r [R],
         options: &PlatformDownloadOptions,
-    ) -> Result<Vec<A>, PlatformError>
+    ) -> Result<&'r R, PlatformError>
     where
         R: Release<A>,
-        A: ReleaseAsset + Clone,
+        A: ReleaseAsset,
     {
-        let release = if let Some(ref tag_name) = options.tag {
-            releases
-                .iter()
-                .find(|release| release.tag_name() == tag_name)
+        let release = if let Some(ref tag) = options.tag {
+            TagConstraint::parse(tag)?.select(releases)
         } else {
             releases
                 .iter()
@@ -249,57 +1181,851 @@ impl<P: ReleasePlatform> ReleaseHandler<'_, P> {
                 .map_or_else(|| releases.first(), Some)
         };
 
-        let Some(release) = release else {
-            return Err(PlatformError::NoRelease {
-                tag: options.tag.clone(),
-            });
-        };
+        release.ok_or_else(|| PlatformError::NoRelease {
+            tag: options.tag.clone(),
+        })
+    }
+
+    pub async fn filter_releases<R, A>(
+        &self,
+        releases: &[R],
+        options: &PlatformDownloadOptions,
+    ) -> Result<Vec<A>, PlatformError>
+    where
+        R: Release<A>,
+        A: ReleaseAsset + Clone,
+    {
+        let release = self.select_release(releases, options)?;
+        filter_assets(release.assets(), options)
+    }
 
-        let assets: Vec<A> = release
-            .assets()
-            .into_iter()
-            .filter(|asset| {
-                let name = asset.name();
-                matches_pattern(
-                    name,
-                    options.regexes.as_slice(),
-                    options.globs.as_slice(),
-                    options.match_keywords.as_slice(),
-                    options.exclude_keywords.as_slice(),
-                    options.exact_case,
-                )
+    /// Fetches releases of `project` (those matching `options.tag` if set) along with the
+    /// assets matching the filters in `options`.
+    pub async fn list_releases<R, A>(
+        &self,
+        project: &str,
+        options: &PlatformDownloadOptions,
+    ) -> Result<Vec<ReleaseInfo>, PlatformError>
+    where
+        R: Release<A> + for<'de> Deserialize<'de>,
+        A: ReleaseAsset,
+    {
+        let constraint = options
+            .tag
+            .as_deref()
+            .map(TagConstraint::parse)
+            .transpose()?;
+        let releases = self
+            .fetch_matching_releases::<R, A>(project, options)
+            .await?;
+
+        Ok(releases
+            .iter()
+            .filter(|release| {
+                constraint.as_ref().is_none_or(|constraint| {
+                    constraint.matches(release.tag_name(), release.is_prerelease())
+                })
             })
-            .collect();
-
-        if assets.is_empty() {
-            return Err(PlatformError::NoMatchingAssets {
-                available_assets: release
-                    .assets()
-                    .into_iter()
-                    .map(|a| a.name().to_string())
+            .map(|release| ReleaseInfo {
+                name: release.name().to_string(),
+                tag_name: release.tag_name().to_string(),
+                prerelease: release.is_prerelease(),
+                published_at: release.published_at().to_string(),
+                assets: select_assets(release.assets(), options)
+                    .iter()
+                    .map(|asset| AssetInfo {
+                        name: asset.name().to_string(),
+                        size: asset.size(),
+                        download_url: asset.download_url().to_string(),
+                    })
                     .collect(),
-            });
+            })
+            .collect())
+    }
+
+    /// Fetches the content of a small `asset`, like a checksum file or signature.
+    async fn fetch_asset<A: ReleaseAsset>(&self, asset: &A) -> Result<Vec<u8>, PlatformError> {
+        let mut attempt = 1;
+        loop {
+            match self.try_fetch_asset(asset).await {
+                Err(err) => {
+                    let Some(delay) = self
+                        .retry
+                        .as_ref()
+                        .and_then(|policy| policy.next_platform_delay(attempt, &err))
+                    else {
+                        return Err(err);
+                    };
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
         }
+    }
 
-        Ok(assets)
+    async fn try_fetch_asset<A: ReleaseAsset>(&self, asset: &A) -> Result<Vec<u8>, PlatformError> {
+        let response = self
+            .downloader
+            .client()
+            .get(asset.download_url())
+            .headers(self.asset_headers(asset)?.unwrap_or_default())
+            .send()
+            .await
+            .map_err(|err| DownloadError::NetworkError { source: err })?;
+
+        if !response.status().is_success() {
+            return Err(DownloadError::ResourceError {
+                url: asset.download_url().to_string(),
+                status: response.status(),
+                retry_after: retry_after(response.headers()),
+            }
+            .into());
+        }
+
+        let content = response
+            .bytes()
+            .await
+            .map_err(|_| PlatformError::InvalidResponse)?;
+
+        Ok(content.to_vec())
+    }
+
+    /// Fetches the checksum of `asset` from the checksum file published in its release,
+    /// if there is one. Errors with [`PlatformError::ChecksumNotFound`] if the checksum
+    /// file doesn't list the asset, which [`Self::download`] downloads unverified.
+    pub async fn fetch_checksum<R, A>(
+        &self,
+        releases: &[R],
+        asset: &A,
+        options: &PlatformDownloadOptions,
+    ) -> Result<Option<Checksum>, PlatformError>
+    where
+        R: Release<A>,
+        A: ReleaseAsset,
+    {
+        let release = self.select_release(releases, options)?;
+        let assets = release.assets();
+        let Some(checksum_asset) = find_checksum_asset(asset.name(), &assets) else {
+            return Ok(None);
+        };
+
+        let content = self.fetch_asset(checksum_asset).await?;
+        let content = String::from_utf8_lossy(&content);
+
+        match Checksum::from_checksum_file(&content, checksum_asset.name(), asset.name()) {
+            Some(checksum) => Ok(Some(checksum)),
+            None => Err(PlatformError::ChecksumNotFound {
+                asset: asset.name().to_string(),
+                checksum_file: checksum_asset.name().to_string(),
+            }),
+        }
     }
 
-    pub async fn download<A: ReleaseAsset>(
+    /// Fetches the detached signature for `asset` when `options.verify_key` is set.
+    pub async fn fetch_signature<R, A>(
+        &self,
+        releases: &[R],
+        asset: &A,
+        options: &PlatformDownloadOptions,
+    ) -> Result<Option<Vec<u8>>, PlatformError>
+    where
+        R: Release<A>,
+        A: ReleaseAsset,
+    {
+        let Some(ref key) = options.verify_key else {
+            return Ok(None);
+        };
+
+        let release = self.select_release(releases, options)?;
+        let assets = release.assets();
+        let signature_asset = key.signature_extensions().iter().find_map(|ext| {
+            let name = format!("{}.{}", asset.name(), ext);
+            assets.iter().find(|a| a.name() == name)
+        });
+
+        let Some(signature_asset) = signature_asset else {
+            return Err(PlatformError::SignatureNotFound {
+                asset: asset.name().to_string(),
+            });
+        };
+
+        Ok(Some(self.fetch_asset(signature_asset).await?))
+    }
+
+    fn asset_headers<A: ReleaseAsset>(
+        &self,
+        asset: &A,
+    ) -> Result<Option<HeaderMap>, PlatformError> {
+        asset
+            .requires_auth()
+            .then(|| self.auth_headers())
+            .transpose()
+    }
+
+    /// Resolves where `asset` would be written and how big it is, without writing
+    /// anything.
+    pub async fn plan<A: ReleaseAsset>(
+        &self,
+        asset: &A,
+        options: &PlatformDownloadOptions,
+    ) -> Result<DownloadPlan, PlatformError> {
+        let mut plan = self
+            .downloader
+            .plan(&DownloadOptions {
+                url: asset.download_url().to_string(),
+                output_path: asset_output(asset, options.output_path.clone()),
+                progress_callback: None,
+                extract_archive: false,
+                extract_dir: None,
+                file_mode: options.file_mode.clone(),
+                prompt: None,
+                checksum: None,
+                signature: None,
+                segments: None,
+                retry: None,
+                dry_run: true,
+                headers: self.asset_headers(asset)?,
+            })
+            .await?;
+        plan.size = plan.size.or(asset.size());
+        Ok(plan)
+    }
+
+    /// Downloads `asset` of one of `releases`, verifying it against the checksum file
+    /// published in the release unless `options.skip_checksum` is set or the checksum
+    /// file doesn't list it. With
+    /// `options.verify_key` set, it's also verified against its detached signature.
+    pub async fn download<R, A>(
+        &self,
+        releases: &[R],
+        asset: &A,
+        mut options: PlatformDownloadOptions,
+    ) -> Result<String, PlatformError>
+    where
+        R: Release<A>,
+        A: ReleaseAsset,
+    {
+        if !options.dry_run && options.checksum.is_none() && !options.skip_checksum {
+            options.checksum = match self.fetch_checksum(releases, asset, &options).await {
+                Err(PlatformError::ChecksumNotFound { .. }) => None,
+                checksum => checksum?,
+            };
+        }
+        if !options.dry_run && options.signature.is_none() {
+            options.signature = self.fetch_signature(releases, asset, &options).await?;
+        }
+        self.download_asset(asset, options).await
+    }
+
+    /// Downloads `asset`, verifying it against `options.checksum` and, with
+    /// `options.verify_key` set, `options.signature`.
+    pub async fn download_asset<A: ReleaseAsset>(
         &self,
         asset: &A,
         options: PlatformDownloadOptions,
     ) -> Result<String, PlatformError> {
+        if options.dry_run {
+            return Ok(self.plan(asset, &options).await?.path);
+        }
+
+        let signature = match options.verify_key {
+            Some(key) => {
+                let Some(signature) = options.signature else {
+                    return Err(PlatformError::SignatureNotFound {
+                        asset: asset.name().to_string(),
+                    });
+                };
+                if options.output_path.as_deref() == Some("-") {
+                    return Err(PlatformError::InvalidInput(
+                        "Signature verification with stdout output".to_string(),
+                    ));
+                }
+                Some(DetachedSignature { key, signature })
+            }
+            None => None,
+        };
+
         Ok(self
             .downloader
             .download(DownloadOptions {
                 url: asset.download_url().to_string(),
-                output_path: options.output_path,
+                output_path: asset_output(asset, options.output_path),
                 progress_callback: options.progress_callback,
                 extract_archive: options.extract_archive,
                 extract_dir: options.extract_dir,
                 file_mode: options.file_mode,
                 prompt: options.prompt,
+                checksum: options.checksum,
+                signature,
+                segments: options.segments,
+                retry: options.retry,
+                dry_run: false,
+                headers: self.asset_headers(asset)?,
             })
             .await?)
     }
+
+    /// Resolves the ref `options.tag` names. Tags, branches and commits are used as they
+    /// are, no tag or a version constraint selects a release like for asset downloads.
+    pub async fn resolve_ref<R, A>(
+        &self,
+        project: &str,
+        options: &PlatformDownloadOptions,
+    ) -> Result<String, PlatformError>
+    where
+        R: Release<A> + for<'de> Deserialize<'de>,
+        A: ReleaseAsset,
+    {
+        if let Some(ref tag) = options.tag {
+            if TagConstraint::parse(tag)?.is_exact() {
+                return Ok(tag.clone());
+            }
+        }
+
+        let releases = self
+            .fetch_matching_releases::<R, A>(project, options)
+            .await?;
+        let release = self.select_release(&releases, options)?;
+        Ok(release.tag_name().to_string())
+    }
+
+    /// URL of `source` in `project` at `git_ref`. Sources are always fetched from the
+    /// primary API.
+    pub fn source_url(
+        &self,
+        project: &str,
+        git_ref: &str,
+        source: &RepoSource,
+    ) -> Result<String, PlatformError> {
+        let base_url = self.base_url.as_deref().unwrap_or(P::API_BASE_PRIMARY);
+        let path = P::format_source_path(project, git_ref, source)?;
+        Ok(format!("{}{}", base_url, path))
+    }
+
+    fn source_options(
+        &self,
+        project: &str,
+        git_ref: &str,
+        source: &RepoSource,
+        options: PlatformDownloadOptions,
+    ) -> Result<DownloadOptions, PlatformError> {
+        let mut headers = self.auth_headers()?;
+        if let Some(accept) = P::source_accept(source) {
+            headers.insert(ACCEPT, HeaderValue::from_static(accept));
+        }
+
+        let file_name = source.file_name(project, git_ref);
+        Ok(DownloadOptions {
+            url: self.source_url(project, git_ref, source)?,
+            output_path: Some(output_file(options.output_path, file_name)),
+            progress_callback: options.progress_callback,
+            extract_archive: options.extract_archive,
+            extract_dir: options.extract_dir,
+            file_mode: options.file_mode,
+            prompt: options.prompt,
+            checksum: options.checksum,
+            signature: None,
+            segments: options.segments,
+            retry: options.retry,
+            dry_run: options.dry_run,
+            headers: Some(headers),
+        })
+    }
+
+    /// Resolves where `source` would be written and how big it is, without writing
+    /// anything.
+    pub async fn plan_source(
+        &self,
+        project: &str,
+        git_ref: &str,
+        source: &RepoSource,
+        options: &PlatformDownloadOptions,
+    ) -> Result<DownloadPlan, PlatformError> {
+        let options = self.source_options(project, git_ref, source, options.clone())?;
+        Ok(self.downloader.plan(&options).await?)
+    }
+
+    /// Downloads `source` from `project` at `git_ref`, see [`Self::resolve_ref`]. The
+    /// token is sent like for API requests.
+    pub async fn download_source(
+        &self,
+        project: &str,
+        git_ref: &str,
+        source: &RepoSource,
+        options: PlatformDownloadOptions,
+    ) -> Result<String, PlatformError> {
+        if options.verify_key.is_some() {
+            return Err(PlatformError::InvalidInput(
+                "Signature verification of repository sources".to_string(),
+            ));
+        }
+
+        let options = self.source_options(project, git_ref, source, options)?;
+        Ok(self.downloader.download(options).await?)
+    }
+}
+
+fn is_not_found(err: &PlatformError) -> bool {
+    matches!(
+        err,
+        PlatformError::DownloadError(DownloadError::ResourceError {
+            status: StatusCode::NOT_FOUND,
+            ..
+        })
+    )
+}
+
+/// URL of the next page from a `Link: <url>; rel="next"` header, or GitLab's
+/// `X-Next-Page` header.
+fn next_page_url(url: &Url, headers: &HeaderMap) -> Option<String> {
+    let link = headers
+        .get_all(LINK)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .find_map(|link| {
+            let (target, params) = link.split_once(';')?;
+            params
+                .split(';')
+                .any(|param| {
+                    param
+                        .trim()
+                        .strip_prefix("rel=")
+                        .is_some_and(|rel| rel.trim_matches('"').split(' ').any(|r| r == "next"))
+                })
+                .then(|| target.trim().trim_start_matches('<').trim_end_matches('>'))
+        });
+    if let Some(link) = link {
+        return url.join(link).ok().map(String::from);
+    }
+
+    let page = headers
+        .get("x-next-page")?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    let mut next = url.clone();
+    let query = url
+        .query_pairs()
+        .filter(|(key, _)| key != "page")
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect::<Vec<_>>();
+    next.query_pairs_mut()
+        .clear()
+        .extend_pairs(query)
+        .append_pair("page", &page.to_string());
+    Some(next.into())
+}
+
+/// Assets matching the filters in `options`, best first when `options.auto` is set, or
+/// [`PlatformError::NoMatchingAssets`] if none match.
+pub fn filter_assets<A: ReleaseAsset>(
+    assets: Vec<A>,
+    options: &PlatformDownloadOptions,
+) -> Result<Vec<A>, PlatformError> {
+    let available_assets = assets.iter().map(|a| a.name().to_string()).collect();
+    let assets = select_assets(assets, options);
+    if assets.is_empty() {
+        return Err(PlatformError::NoMatchingAssets { available_assets });
+    }
+    Ok(assets)
+}
+
+fn select_assets<A: ReleaseAsset>(assets: Vec<A>, options: &PlatformDownloadOptions) -> Vec<A> {
+    let assets = assets
+        .into_iter()
+        .filter(|asset| matches_asset(asset.name(), options));
+    let Some(ref target) = options.auto else {
+        return assets.collect();
+    };
+
+    let mut scored = assets
+        .filter_map(|asset| Some((target.score(asset.name())?, asset)))
+        .collect::<Vec<_>>();
+    scored.sort_by_key(|(score, _)| Reverse(*score));
+    scored.into_iter().map(|(_, asset)| asset).collect()
+}
+
+fn asset_output<A: ReleaseAsset>(asset: &A, output_path: Option<String>) -> Option<String> {
+    match asset.file_name() {
+        Some(file_name) => Some(output_file(output_path, file_name)),
+        None => output_path,
+    }
+}
+
+/// `output_path`, joined with `file_name` if it's a directory, or `file_name` if not set.
+fn output_file(output_path: Option<String>, file_name: String) -> String {
+    match output_path {
+        Some(ref out) if out.ends_with('/') || Path::new(out).is_dir() => {
+            Path::new(out).join(file_name).to_string_lossy().into()
+        }
+        Some(out) => out,
+        None => file_name,
+    }
+}
+
+fn matches_asset(name: &str, options: &PlatformDownloadOptions) -> bool {
+    matches_pattern(
+        name,
+        options.regexes.as_slice(),
+        options.globs.as_slice(),
+        options.match_keywords.as_slice(),
+        options.exclude_keywords.as_slice(),
+        options.exact_case,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        gitea::Gitea,
+        github::{Github, GithubArtifact, GithubAsset},
+    };
+
+    fn target(triple: &str) -> AssetTarget {
+        AssetTarget::parse(triple).unwrap()
+    }
+
+    #[test]
+    fn asset_target_parse() {
+        assert_eq!(
+            target("x86_64-unknown-linux-musl"),
+            AssetTarget {
+                arch: "x86_64".to_string(),
+                os: "linux".to_string(),
+                libc: Some("musl".to_string()),
+            }
+        );
+        assert_eq!(
+            target("aarch64-unknown-linux").to_string(),
+            "aarch64-linux-gnu"
+        );
+        assert_eq!(target("arm64-apple-darwin").to_string(), "aarch64-macos");
+        assert_eq!(
+            target("x86_64-pc-windows-msvc").to_string(),
+            "x86_64-windows"
+        );
+        assert_eq!(AssetTarget::parse("x86_64"), None);
+        assert_eq!(AssetTarget::parse("sparc-unknown-linux"), None);
+    }
+
+    #[test]
+    fn asset_target_score_rejects_other_platforms() {
+        let linux = target("x86_64-unknown-linux-gnu");
+        assert_eq!(linux.score("tool-aarch64-linux"), None);
+        assert_eq!(linux.score("tool-x86_64-darwin.tar.gz"), None);
+        assert!(linux.score("tool-amd64-linux").is_some());
+
+        let musl = target("x86_64-unknown-linux-musl");
+        assert_eq!(musl.score("tool-x86_64-linux-gnu"), None);
+        assert!(musl.score("tool-x86_64-linux-musl").is_some());
+    }
+
+    #[test]
+    fn asset_target_score_ranks() {
+        let linux = target("x86_64-unknown-linux-gnu");
+        let score = |name| linux.score(name).unwrap();
+        assert!(score("tool-x86_64-linux-gnu.tar.gz") > score("tool-x86_64-linux.tar.gz"));
+        assert!(score("tool-x86_64-linux.tar.gz") > score("tool-x86_64.tar.gz"));
+        assert!(score("tool-x86_64-linux.tar.gz") > score("tool-x86_64-linux.deb"));
+        assert!(score("tool") > score("tool-x86_64-linux.tar.gz.sha256"));
+        assert!(score("tool") > score("tool-x86_64-linux.tar.gz.sig"));
+        assert!(score("tool") > score("tool-src.tar.gz"));
+
+        let mac = target("aarch64-apple-darwin");
+        assert!(mac.score("tool-universal-macos").unwrap() > mac.score("tool-macos").unwrap());
+    }
+
+    #[test]
+    fn asset_target_score_darwin_on_macos() {
+        // what `AssetTarget::host` reports on macOS
+        let mac = AssetTarget {
+            arch: "aarch64".to_string(),
+            os: "macos".to_string(),
+            libc: None,
+        };
+        let score = |name| mac.score(name).unwrap();
+        assert!(score("tool-aarch64-apple-darwin.tar.gz") > score("tool-aarch64.tar.gz"));
+        assert!(score("tool-darwin-arm64.tar.gz") > score("tool-arm64.tar.gz"));
+        assert!(score("tool-osx-arm64.zip") > score("tool-arm64.zip"));
+        assert_eq!(mac.score("tool-aarch64-linux"), None);
+    }
+
+    fn assets(names: &[&str]) -> Vec<GithubAsset> {
+        names
+            .iter()
+            .map(|name| GithubAsset {
+                name: name.to_string(),
+                size: 0,
+                browser_download_url: format!("https://example.com/{}", name),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn checksum_asset_lookup() {
+        let find = |asset, names: &[&str]| {
+            find_checksum_asset(asset, &assets(names)).map(|asset| asset.name.clone())
+        };
+        let names = [
+            "tool",
+            "tool.sha256",
+            "other",
+            "SHA256SUMS",
+            "SHA256SUMS.sig",
+        ];
+        assert_eq!(find("tool", &names).as_deref(), Some("tool.sha256"));
+        assert_eq!(find("other", &names).as_deref(), Some("SHA256SUMS"));
+        // checksum files and signatures aren't verified themselves
+        assert_eq!(find("SHA256SUMS", &names), None);
+        assert_eq!(find("tool.sha256", &names), None);
+        assert_eq!(find("SHA256SUMS.sig", &names), None);
+        assert_eq!(
+            find("tool.minisig", &["tool.minisig", "checksums.txt"]),
+            None
+        );
+        assert_eq!(find("tool", &["tool", "tool.sig"]), None);
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn next_page_from_link() {
+        let url = Url::parse("https://api.github.com/repos/o/r/releases?per_page=100").unwrap();
+        let link = r#"<https://api.github.com/repositories/1/releases?per_page=100&page=2>; rel="next", <https://api.github.com/repositories/1/releases?per_page=100&page=5>; rel="last""#;
+        assert_eq!(
+            next_page_url(&url, &headers(&[("link", link)])).as_deref(),
+            Some("https://api.github.com/repositories/1/releases?per_page=100&page=2")
+        );
+
+        let last = r#"<https://api.github.com/repositories/1/releases?page=4>; rel="prev""#;
+        assert_eq!(next_page_url(&url, &headers(&[("link", last)])), None);
+    }
+
+    #[test]
+    fn next_page_from_relative_link() {
+        let url = Url::parse("https://codeberg.org/api/v1/repos/o/r/releases?limit=50").unwrap();
+        assert_eq!(
+            next_page_url(
+                &url,
+                &headers(&[(
+                    "link",
+                    r#"</api/v1/repos/o/r/releases?limit=50&page=2>; rel="next""#
+                )])
+            )
+            .as_deref(),
+            Some("https://codeberg.org/api/v1/repos/o/r/releases?limit=50&page=2")
+        );
+    }
+
+    #[test]
+    fn next_page_from_gitlab_header() {
+        let url = Url::parse("https://gitlab.com/api/v4/projects/1/releases?per_page=100&page=1")
+            .unwrap();
+        assert_eq!(
+            next_page_url(&url, &headers(&[("x-next-page", "2")])).as_deref(),
+            Some("https://gitlab.com/api/v4/projects/1/releases?per_page=100&page=2")
+        );
+        assert_eq!(next_page_url(&url, &headers(&[("x-next-page", "")])), None);
+        assert_eq!(next_page_url(&url, &HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn forge_host_parse() {
+        let host = ForgeHost::parse("gitlab:git.corp.example").unwrap();
+        assert_eq!(host.kind, ForgeKind::Gitlab);
+        assert_eq!(host.url, "https://git.corp.example");
+        assert_eq!(host.host(), "git.corp.example");
+
+        let host = ForgeHost::parse("GitHub:http://127.0.0.1:8080/").unwrap();
+        assert_eq!(host.kind, ForgeKind::Github);
+        assert_eq!(host.url, "http://127.0.0.1:8080");
+        assert_eq!(host.host(), "127.0.0.1:8080");
+
+        assert_eq!(
+            ForgeHost::parse("forgejo:git.example").unwrap().kind,
+            ForgeKind::Gitea
+        );
+        assert!(ForgeHost::parse("git.corp.example").is_none());
+        assert!(ForgeHost::parse("bitbucket:bitbucket.org").is_none());
+        assert!(ForgeHost::parse("gitlab:").is_none());
+    }
+
+    #[test]
+    fn split_instance_by_url_or_host() {
+        let hosts = [ForgeHost::parse("gitlab:git.corp.example").unwrap()];
+        assert_eq!(
+            split_instance("https://other.example/group/sub/project@v1", &hosts),
+            (
+                Some("https://other.example".to_string()),
+                "group/sub/project@v1"
+            )
+        );
+        assert_eq!(
+            split_instance("git.corp.example/group/project", &hosts),
+            (
+                Some("https://git.corp.example".to_string()),
+                "group/project"
+            )
+        );
+        assert_eq!(
+            split_instance("other.example/group/project", &hosts),
+            (None, "other.example/group/project")
+        );
+        assert_eq!(split_instance("owner/repo", &[]), (None, "owner/repo"));
+    }
+
+    #[test]
+    fn token_var_per_host() {
+        assert_eq!(
+            host_token_var("GITLAB_TOKEN", "git.corp.example"),
+            "GITLAB_TOKEN_GIT_CORP_EXAMPLE"
+        );
+        assert_eq!(
+            host_token_var("GITHUB_TOKEN", "127.0.0.1:8080"),
+            "GITHUB_TOKEN_127_0_0_1_8080"
+        );
+    }
+
+    #[test]
+    fn api_preference_parse() {
+        assert_eq!(
+            ApiPreference::parse("pkgforge-first"),
+            Some(ApiPreference::PkgforgeFirst)
+        );
+        assert_eq!(
+            ApiPreference::parse(" primary "),
+            Some(ApiPreference::PrimaryOnly)
+        );
+        assert_eq!(
+            ApiPreference::parse("pkgforge-only"),
+            Some(ApiPreference::PkgforgeOnly)
+        );
+        assert_eq!(
+            ApiPreference::parse("github=https://gh-mirror.example.com/"),
+            Some(ApiPreference::Mirror(
+                ForgeKind::Github,
+                "https://gh-mirror.example.com".to_string()
+            ))
+        );
+        assert_eq!(ApiPreference::parse("https://gh-mirror.example.com"), None);
+        assert_eq!(ApiPreference::parse("github=ftp://mirror.example"), None);
+        assert_eq!(
+            ApiPreference::parse("bitbucket=https://mirror.example"),
+            None
+        );
+        assert_eq!(ApiPreference::parse("mirror"), None);
+    }
+
+    #[test]
+    fn repo_source_archive() {
+        assert_eq!(
+            RepoSource::split("owner/repo@v1.0#source"),
+            (
+                "owner/repo@v1.0",
+                Some(RepoSource::Archive(ArchiveFormat::TarGz))
+            )
+        );
+        assert_eq!(
+            RepoSource::split("owner/repo#ZIPBALL"),
+            ("owner/repo", Some(RepoSource::Archive(ArchiveFormat::Zip)))
+        );
+        assert_eq!(
+            RepoSource::split("owner/repo@v1#other"),
+            ("owner/repo@v1#other", None)
+        );
+    }
+
+    #[test]
+    fn repo_source_file() {
+        assert_eq!(
+            RepoSource::split("owner/repo@main:/install.sh"),
+            (
+                "owner/repo@main",
+                Some(RepoSource::File("install.sh".to_string()))
+            )
+        );
+        // the path is taken as is, archive suffixes included
+        assert_eq!(
+            RepoSource::split("owner/repo@feat/x:docs/notes#source"),
+            (
+                "owner/repo@feat/x",
+                Some(RepoSource::File("docs/notes#source".to_string()))
+            )
+        );
+        assert_eq!(
+            RepoSource::split("owner/repo@main:"),
+            ("owner/repo@main:", None)
+        );
+        assert_eq!(RepoSource::split("owner/repo"), ("owner/repo", None));
+    }
+
+    #[test]
+    fn repo_source_file_name() {
+        assert_eq!(
+            RepoSource::Archive(ArchiveFormat::TarGz).file_name("group/project", "feat/x"),
+            "project-feat-x.tar.gz"
+        );
+        assert_eq!(
+            RepoSource::File("docs/install.sh".to_string()).file_name("repo", "main"),
+            "install.sh"
+        );
+    }
+
+    #[test]
+    fn release_api_path_encodes_tag() {
+        assert_eq!(
+            Github::format_api_path("o/r", Some("cli/v1.0+1")).unwrap(),
+            "/repos/o/r/releases/tags/cli%2Fv1.0%2B1?per_page=100"
+        );
+        assert_eq!(
+            Gitea::format_api_path("o/r", Some("cli/v1.0+1")).unwrap(),
+            "/api/v1/repos/o/r/releases/tags/cli%2Fv1.0%2B1"
+        );
+    }
+
+    #[test]
+    fn artifact_download_sends_token_and_zip_name() {
+        let artifact = GithubArtifact {
+            id: 1,
+            name: "build".to_string(),
+            size_in_bytes: 6,
+            archive_download_url: "https://api.github.com/repos/o/r/actions/artifacts/1/zip"
+                .to_string(),
+            expired: false,
+            digest: None,
+        };
+        let release_asset = GithubAsset {
+            name: "tool".to_string(),
+            size: 6,
+            browser_download_url: "https://example.com/tool".to_string(),
+        };
+        let handler = ReleaseHandler::<Github>::new().with_token("secret");
+
+        let headers = handler.asset_headers(&artifact).unwrap().unwrap();
+        assert_eq!(headers[AUTHORIZATION], "Bearer secret");
+        // release assets are public downloads, the token only goes to the API
+        assert!(handler.asset_headers(&release_asset).unwrap().is_none());
+
+        assert_eq!(asset_output(&artifact, None).as_deref(), Some("build.zip"));
+        assert_eq!(
+            asset_output(&artifact, Some("out/".to_string())).as_deref(),
+            Some("out/build.zip")
+        );
+        assert_eq!(
+            asset_output(&artifact, Some("artifact.zip".to_string())).as_deref(),
+            Some("artifact.zip")
+        );
+        assert_eq!(asset_output(&release_asset, None), None);
+    }
 }
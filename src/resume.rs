@@ -12,30 +12,64 @@ pub struct ResumeSupport;
 pub struct DownloadMeta {
     etag: Option<String>,
     last_modified: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    segments: Vec<SegmentProgress>,
+}
+
+/// Byte range `start..=end` of a segmented download, of which `written` bytes are on disk.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SegmentProgress {
+    pub start: u64,
+    pub end: u64,
+    pub written: u64,
 }
 
 impl ResumeSupport {
-    pub async fn read_metadata<P: AsRef<Path>>(
-        meta_path: P,
-    ) -> Result<(Option<String>, Option<String>), DownloadError> {
+    async fn read_meta<P: AsRef<Path>>(meta_path: P) -> Result<DownloadMeta, DownloadError> {
         if fs::try_exists(meta_path.as_ref()).await? {
             let data = fs::read_to_string(meta_path).await?;
-            let meta: DownloadMeta =
-                serde_json::from_str(&data).map_err(|_| DownloadError::InvalidResponse)?;
-            Ok((meta.etag, meta.last_modified))
+            serde_json::from_str(&data).map_err(|_| DownloadError::InvalidResponse)
         } else {
-            Ok((None, None))
+            Ok(DownloadMeta {
+                etag: None,
+                last_modified: None,
+                segments: Vec::new(),
+            })
         }
     }
 
+    pub async fn read_metadata<P: AsRef<Path>>(
+        meta_path: P,
+    ) -> Result<(Option<String>, Option<String>), DownloadError> {
+        let meta = Self::read_meta(meta_path).await?;
+        Ok((meta.etag, meta.last_modified))
+    }
+
     pub async fn write_metadata<P: AsRef<Path>>(
         meta_path: P,
         etag: Option<String>,
         last_modified: Option<String>,
+    ) -> Result<(), DownloadError> {
+        Self::write_segments(meta_path, etag, last_modified, Vec::new()).await
+    }
+
+    /// Reads the progress of a segmented download, empty for single-stream downloads.
+    pub async fn read_segments<P: AsRef<Path>>(
+        meta_path: P,
+    ) -> Result<Vec<SegmentProgress>, DownloadError> {
+        Ok(Self::read_meta(meta_path).await?.segments)
+    }
+
+    pub async fn write_segments<P: AsRef<Path>>(
+        meta_path: P,
+        etag: Option<String>,
+        last_modified: Option<String>,
+        segments: Vec<SegmentProgress>,
     ) -> Result<(), DownloadError> {
         let meta = DownloadMeta {
             etag,
             last_modified,
+            segments,
         };
         fs::write(meta_path, serde_json::to_string(&meta).unwrap()).await?;
         Ok(())
@@ -0,0 +1,207 @@
+use std::time::{Duration, SystemTime};
+
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    StatusCode,
+};
+
+use crate::error::{DownloadError, PlatformError};
+
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: bool,
+    pub respect_retry_after: bool,
+    pub retryable_statuses: Vec<StatusCode>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+            respect_retry_after: true,
+            retryable_statuses: vec![
+                StatusCode::REQUEST_TIMEOUT,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn is_retryable(&self, err: &DownloadError) -> bool {
+        match err {
+            DownloadError::NetworkError { .. } | DownloadError::ChunkError => true,
+            DownloadError::ResourceError { status, .. } => self.retryable_statuses.contains(status),
+            _ => false,
+        }
+    }
+
+    /// Returns how long to wait before the next attempt, or `None` if `err` should not
+    /// be retried. `attempt` is the number of attempts made so far. A `Retry-After` longer
+    /// than `max_delay` isn't waited for, retrying earlier would fail again.
+    pub fn next_delay(&self, attempt: u32, err: &DownloadError) -> Option<Duration> {
+        if attempt >= self.max_attempts || !self.is_retryable(err) {
+            return None;
+        }
+
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        let delay = if self.jitter {
+            let half = exp / 2;
+            half + half.mul_f64(fastrand::f64())
+        } else {
+            exp
+        };
+
+        let retry_after = match err {
+            DownloadError::ResourceError { retry_after, .. } if self.respect_retry_after => {
+                *retry_after
+            }
+            _ => None,
+        };
+
+        match retry_after {
+            Some(after) if after > self.max_delay => None,
+            Some(after) => Some(after.max(delay)),
+            None => Some(delay),
+        }
+    }
+
+    pub fn next_platform_delay(&self, attempt: u32, err: &PlatformError) -> Option<Duration> {
+        match err {
+            PlatformError::DownloadError(err) => self.next_delay(attempt, err),
+            _ => None,
+        }
+    }
+}
+
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    httpdate::parse_http_date(value)
+        .ok()?
+        .duration_since(SystemTime::now())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 5,
+            jitter: false,
+            ..Default::default()
+        }
+    }
+
+    fn status(status: StatusCode, retry_after: Option<Duration>) -> DownloadError {
+        DownloadError::ResourceError {
+            url: "https://example.com".to_string(),
+            status,
+            retry_after,
+        }
+    }
+
+    #[test]
+    fn exponential_backoff() {
+        let policy = policy();
+        let err = DownloadError::ChunkError;
+        assert_eq!(policy.next_delay(1, &err), Some(Duration::from_secs(1)));
+        assert_eq!(policy.next_delay(2, &err), Some(Duration::from_secs(2)));
+        assert_eq!(policy.next_delay(3, &err), Some(Duration::from_secs(4)));
+        assert_eq!(policy.next_delay(5, &err), None);
+    }
+
+    #[test]
+    fn backoff_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 40,
+            ..policy()
+        };
+        let err = DownloadError::ChunkError;
+        assert_eq!(policy.next_delay(10, &err), Some(policy.max_delay));
+        assert_eq!(policy.next_delay(39, &err), Some(policy.max_delay));
+    }
+
+    #[test]
+    fn jitter_within_bounds() {
+        let policy = RetryPolicy {
+            jitter: true,
+            ..policy()
+        };
+        for _ in 0..100 {
+            let delay = policy.next_delay(3, &DownloadError::ChunkError).unwrap();
+            assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(4));
+        }
+    }
+
+    #[test]
+    fn retryable_statuses() {
+        let policy = policy();
+        assert!(policy
+            .next_delay(1, &status(StatusCode::SERVICE_UNAVAILABLE, None))
+            .is_some());
+        assert_eq!(
+            policy.next_delay(1, &status(StatusCode::NOT_FOUND, None)),
+            None
+        );
+        assert_eq!(policy.next_delay(1, &DownloadError::InvalidResponse), None);
+    }
+
+    #[test]
+    fn honors_retry_after() {
+        let policy = policy();
+        let err = status(StatusCode::TOO_MANY_REQUESTS, Some(Duration::from_secs(10)));
+        assert_eq!(policy.next_delay(1, &err), Some(Duration::from_secs(10)));
+
+        // the backoff wins if it's longer
+        let err = status(StatusCode::TOO_MANY_REQUESTS, Some(Duration::ZERO));
+        assert_eq!(policy.next_delay(2, &err), Some(Duration::from_secs(2)));
+
+        let ignored = RetryPolicy {
+            respect_retry_after: false,
+            ..policy
+        };
+        let err = status(StatusCode::TOO_MANY_REQUESTS, Some(Duration::from_secs(10)));
+        assert_eq!(ignored.next_delay(1, &err), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn gives_up_on_long_retry_after() {
+        let err = status(
+            StatusCode::TOO_MANY_REQUESTS,
+            Some(Duration::from_secs(3600)),
+        );
+        assert_eq!(policy().next_delay(1, &err), None);
+    }
+
+    #[test]
+    fn retry_after_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert(RETRY_AFTER, "120".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(120)));
+        headers.insert(
+            RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), None);
+    }
+}
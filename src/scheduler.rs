@@ -0,0 +1,78 @@
+use std::{future::Future, sync::Arc};
+
+use tokio::{sync::Semaphore, task::JoinHandle};
+
+/// Runs spawned tasks with at most `concurrency` of them in progress at once.
+#[derive(Clone)]
+pub struct Scheduler {
+    semaphore: Arc<Semaphore>,
+}
+
+impl Scheduler {
+    pub fn new(concurrency: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(concurrency.max(1))),
+        }
+    }
+
+    /// Spawns `future` once a slot is free. The slot is held until the future completes.
+    pub fn spawn<F, T>(&self, future: F) -> JoinHandle<T>
+    where
+        F: Future<Output = T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let semaphore = self.semaphore.clone();
+        tokio::spawn(async move {
+            let _permit = semaphore
+                .acquire_owned()
+                .await
+                .expect("scheduler semaphore is never closed");
+            future.await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use tokio::{runtime::Runtime, time::sleep};
+
+    use super::*;
+
+    fn max_in_flight(concurrency: usize, tasks: usize) -> usize {
+        Runtime::new().unwrap().block_on(async {
+            let scheduler = Scheduler::new(concurrency);
+            let running = Arc::new(AtomicUsize::new(0));
+            let peak = Arc::new(AtomicUsize::new(0));
+
+            let handles: Vec<_> = (0..tasks)
+                .map(|_| {
+                    let running = running.clone();
+                    let peak = peak.clone();
+                    scheduler.spawn(async move {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+                        sleep(Duration::from_millis(20)).await;
+                        running.fetch_sub(1, Ordering::SeqCst);
+                    })
+                })
+                .collect();
+            for handle in handles {
+                handle.await.unwrap();
+            }
+            peak.load(Ordering::SeqCst)
+        })
+    }
+
+    #[test]
+    fn scheduler_bounds_running_tasks() {
+        assert_eq!(max_in_flight(2, 8), 2);
+        assert_eq!(max_in_flight(1, 4), 1);
+        // zero is treated as one so the tasks still run
+        assert_eq!(max_in_flight(0, 3), 1);
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    fs::Permissions,
+    os::unix::fs::PermissionsExt,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use p256::{
+    ecdsa::{signature::DigestVerifier, DerSignature, VerifyingKey},
+    pkcs8::DecodePublicKey,
+};
+use sha2::{Digest, Sha256};
+use tokio::{
+    fs::{self, File},
+    io::{AsyncReadExt, BufReader},
+    task,
+};
+
+use crate::error::DownloadError;
+
+#[derive(Clone)]
+pub enum VerifyKey {
+    Minisign(minisign_verify::PublicKey),
+    Cosign(VerifyingKey),
+    Gpg(String),
+}
+
+impl VerifyKey {
+    /// Parses a minisign public key, a cosign (ECDSA P-256) PEM public key or an
+    /// armored GPG public key block.
+    pub fn parse(content: &str) -> Result<Self, DownloadError> {
+        let content = content.trim();
+
+        if content.starts_with("-----BEGIN PGP PUBLIC KEY BLOCK-----") {
+            return Ok(Self::Gpg(content.to_string()));
+        }
+
+        if content.starts_with("-----BEGIN PUBLIC KEY-----") {
+            return VerifyingKey::from_public_key_pem(content)
+                .map(Self::Cosign)
+                .map_err(|err| DownloadError::SignatureError(err.to_string()));
+        }
+
+        let key = if content.starts_with("untrusted comment:") {
+            minisign_verify::PublicKey::decode(content)
+        } else {
+            minisign_verify::PublicKey::from_base64(content)
+        };
+        key.map(Self::Minisign)
+            .map_err(|err| DownloadError::SignatureError(err.to_string()))
+    }
+
+    /// Signature file extensions, in order of preference, that are published next to
+    /// the signed file.
+    pub fn signature_extensions(&self) -> &'static [&'static str] {
+        match self {
+            VerifyKey::Minisign(_) => &["minisig"],
+            VerifyKey::Cosign(_) => &["sig"],
+            VerifyKey::Gpg(_) => &["asc", "sig", "gpg"],
+        }
+    }
+
+    pub async fn verify<P: AsRef<Path>>(
+        &self,
+        file_path: P,
+        signature: &[u8],
+    ) -> Result<(), DownloadError> {
+        let file_path = file_path.as_ref();
+        match self {
+            VerifyKey::Minisign(key) => verify_minisign(key, file_path, signature).await,
+            VerifyKey::Cosign(key) => verify_cosign(key, file_path, signature).await,
+            VerifyKey::Gpg(key) => verify_gpg(key, file_path, signature).await,
+        }
+    }
+}
+
+/// A detached `signature` to verify a file against with `key`.
+#[derive(Clone)]
+pub struct DetachedSignature {
+    pub key: VerifyKey,
+    pub signature: Vec<u8>,
+}
+
+impl DetachedSignature {
+    pub async fn verify<P: AsRef<Path>>(&self, file_path: P) -> Result<(), DownloadError> {
+        self.key.verify(file_path, &self.signature).await
+    }
+}
+
+async fn read_chunks<F>(file_path: &Path, mut update: F) -> Result<(), DownloadError>
+where
+    F: FnMut(&[u8]),
+{
+    let mut file = BufReader::new(File::open(file_path).await?);
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        update(&buf[..n]);
+    }
+    Ok(())
+}
+
+async fn verify_minisign(
+    key: &minisign_verify::PublicKey,
+    file_path: &Path,
+    signature: &[u8],
+) -> Result<(), DownloadError> {
+    let signature = minisign_verify::Signature::decode(&String::from_utf8_lossy(signature))
+        .map_err(|err| DownloadError::SignatureError(err.to_string()))?;
+
+    match key.verify_stream(&signature) {
+        Ok(mut verifier) => {
+            read_chunks(file_path, |chunk| verifier.update(chunk)).await?;
+            verifier.finalize()
+        }
+        // legacy signatures are computed over the whole file
+        Err(minisign_verify::Error::UnsupportedLegacyMode) => {
+            let data = fs::read(file_path).await?;
+            key.verify(&data, &signature, true)
+        }
+        Err(err) => Err(err),
+    }
+    .map_err(|err| DownloadError::SignatureError(err.to_string()))
+}
+
+async fn verify_cosign(
+    key: &VerifyingKey,
+    file_path: &Path,
+    signature: &[u8],
+) -> Result<(), DownloadError> {
+    let encoded = String::from_utf8_lossy(signature);
+    let der = STANDARD
+        .decode(encoded.trim())
+        .unwrap_or_else(|_| signature.to_vec());
+    let signature = DerSignature::from_bytes(&der)
+        .map_err(|err| DownloadError::SignatureError(err.to_string()))?;
+
+    let mut hasher = Sha256::new();
+    read_chunks(file_path, |chunk| hasher.update(chunk)).await?;
+
+    key.verify_digest(hasher, &signature)
+        .map_err(|err| DownloadError::SignatureError(err.to_string()))
+}
+
+async fn verify_gpg(key: &str, file_path: &Path, signature: &[u8]) -> Result<(), DownloadError> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let home = std::env::temp_dir().join(format!("soar-dl-gpg-{}-{}", std::process::id(), nanos));
+    fs::create_dir_all(&home).await?;
+    fs::set_permissions(&home, Permissions::from_mode(0o700)).await?;
+
+    let key_path = home.join("key.asc");
+    let sig_path = home.join("file.sig");
+    fs::write(&key_path, key).await?;
+    fs::write(&sig_path, signature).await?;
+
+    let file_path = file_path.to_path_buf();
+    let result = task::spawn_blocking({
+        let home = home.clone();
+        move || run_gpg(&home, &key_path, &sig_path, &file_path)
+    })
+    .await
+    .map_err(|err| DownloadError::SignatureError(err.to_string()))
+    .and_then(|r| r);
+
+    fs::remove_dir_all(&home).await.ok();
+    result
+}
+
+fn run_gpg(
+    home: &Path,
+    key_path: &Path,
+    sig_path: &Path,
+    file_path: &Path,
+) -> Result<(), DownloadError> {
+    let gpg = |args: &[&std::ffi::OsStr]| {
+        Command::new("gpg")
+            .arg("--batch")
+            .arg("--quiet")
+            .arg("--homedir")
+            .arg(home)
+            .args(args)
+            .output()
+            .map_err(|err| DownloadError::SignatureError(format!("failed to run gpg: {}", err)))
+    };
+
+    let import = gpg(&["--import".as_ref(), key_path.as_os_str()])?;
+    if !import.status.success() {
+        return Err(DownloadError::SignatureError(
+            String::from_utf8_lossy(&import.stderr).trim().to_string(),
+        ));
+    }
+
+    let verify = gpg(&[
+        "--verify".as_ref(),
+        sig_path.as_os_str(),
+        file_path.as_os_str(),
+    ])?;
+    if !verify.status.success() {
+        return Err(DownloadError::SignatureError(
+            String::from_utf8_lossy(&verify.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(())
+}
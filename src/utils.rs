@@ -2,8 +2,10 @@ use std::{
     env,
     io::Write,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
+use compak::Archive;
 use fast_glob::glob_match;
 use regex::Regex;
 use reqwest::StatusCode;
@@ -122,6 +124,28 @@ pub fn decode_uri(s: impl AsRef<str>) -> String {
     .into_owned()
 }
 
+/// Percent-encodes everything but unreserved characters, for use in a URL path segment.
+pub fn encode_uri_component(s: impl AsRef<str>) -> String {
+    s.as_ref()
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Percent-encodes each segment of `path`, keeping the `/` between them.
+pub fn encode_uri_path(path: impl AsRef<str>) -> String {
+    path.as_ref()
+        .split('/')
+        .map(encode_uri_component)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
 pub fn build_absolute_path<P: AsRef<Path>>(path: P) -> std::io::Result<PathBuf> {
     let path = path.as_ref();
     if path.is_absolute() {
@@ -131,6 +155,27 @@ pub fn build_absolute_path<P: AsRef<Path>>(path: P) -> std::io::Result<PathBuf>
     }
 }
 
+pub async fn extract_archive<P: AsRef<Path>>(
+    path: P,
+    extract_dir: Option<&str>,
+) -> Result<(), DownloadError> {
+    let path = path.as_ref();
+    let extract_dir = match extract_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let path = build_absolute_path(path)?;
+            path.parent()
+                .map(|p| p.to_path_buf())
+                .unwrap_or_else(|| PathBuf::from("."))
+        }
+    };
+    let archive = Archive::new(path)?;
+    archive.extract_to(&extract_dir).await?;
+    Ok(())
+}
+
+pub type PromptCallback = Arc<dyn Fn(&str) -> Result<bool, DownloadError> + Send + Sync + 'static>;
+
 #[derive(Clone)]
 pub enum FileMode {
     SkipExisting,
@@ -155,3 +200,16 @@ pub fn default_prompt_confirm(file_name: &str) -> Result<bool, DownloadError> {
     std::io::stdin().read_line(&mut line)?;
     Ok(matches!(line.trim().to_lowercase().as_str(), "y" | "yes"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_uri() {
+        assert_eq!(encode_uri_component("feat/a b#1"), "feat%2Fa%20b%231");
+        assert_eq!(encode_uri_component("v1.2.3-rc_1~"), "v1.2.3-rc_1~");
+        assert_eq!(encode_uri_path("docs/a b?.md"), "docs/a%20b%3F.md");
+        assert_eq!(decode_uri(encode_uri_component("ü/x y")), "ü/x y");
+    }
+}
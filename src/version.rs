@@ -0,0 +1,334 @@
+use semver::{Prerelease, Version, VersionReq};
+
+use crate::{
+    error::PlatformError,
+    platform::{Release, ReleaseAsset},
+};
+
+pub const LATEST_PRERELEASE: &str = "latest-prerelease";
+
+/// How a requested tag selects a release.
+#[derive(Clone, Debug)]
+pub enum TagConstraint {
+    /// The release with this tag name, with or without a leading `v`
+    Exact(String),
+    /// The highest version, prereleases included
+    LatestPrerelease,
+    /// The highest stable version matching a semver requirement such as `^1.4`, `~2.0`
+    /// or `>=3,<4`. Only tags with `prefix` are considered, e.g. `cli-` for `cli-^2.0`,
+    /// or those with the prefix of the latest release if it's not given.
+    Requirement {
+        prefix: Option<String>,
+        req: VersionReq,
+    },
+}
+
+impl TagConstraint {
+    pub fn parse(tag: &str) -> Result<Self, PlatformError> {
+        let tag = tag.trim();
+        if tag == LATEST_PRERELEASE {
+            return Ok(TagConstraint::LatestPrerelease);
+        }
+
+        let (prefix, req) = match tag.find(['^', '~', '>', '<', '=', '*']) {
+            Some(i) if !tag[..i].contains(',') => tag.split_at(i),
+            _ if tag.contains(',') => ("", tag),
+            _ => return Ok(TagConstraint::Exact(tag.to_string())),
+        };
+        let prefix = trim_version_marker(prefix);
+        // `foo=bar` is a tag name rather than a prefixed requirement
+        if prefix.ends_with(|c: char| c.is_ascii_alphanumeric()) {
+            return Ok(TagConstraint::Exact(tag.to_string()));
+        }
+
+        VersionReq::parse(req)
+            .map(|req| TagConstraint::Requirement {
+                prefix: (!prefix.is_empty()).then(|| prefix.to_string()),
+                req,
+            })
+            .map_err(|_| PlatformError::InvalidInput(tag.to_string()))
+    }
+
+    /// Whether this names a single tag, which can be fetched directly.
+    pub fn is_exact(&self) -> bool {
+        matches!(self, TagConstraint::Exact(_))
+    }
+
+    pub fn matches(&self, tag_name: &str, prerelease: bool) -> bool {
+        match self {
+            TagConstraint::Exact(tag) => {
+                tag == tag_name || alternate_tag(tag).is_some_and(|tag| tag == tag_name)
+            }
+            TagConstraint::LatestPrerelease => tag_version(tag_name).is_some(),
+            TagConstraint::Requirement { prefix, req } => {
+                !prerelease
+                    && prefix
+                        .as_deref()
+                        .is_none_or(|prefix| tag_prefix(tag_name) == Some(prefix))
+                    && tag_version(tag_name).is_some_and(|version| req.matches(&version))
+            }
+        }
+    }
+
+    pub fn select<'r, R, A>(&self, releases: &'r [R]) -> Option<&'r R>
+    where
+        R: Release<A>,
+        A: ReleaseAsset,
+    {
+        if let TagConstraint::Exact(tag) = self {
+            return releases
+                .iter()
+                .find(|release| release.tag_name() == tag)
+                .or_else(|| {
+                    releases
+                        .iter()
+                        .find(|release| self.matches(release.tag_name(), false))
+                });
+        }
+
+        // versions of different tag families, e.g. `cli-v2.0` and `lib-v3.0` in a monorepo,
+        // aren't comparable
+        let prefix = match self {
+            TagConstraint::Requirement {
+                prefix: Some(prefix),
+                ..
+            } => Some(prefix.as_str()),
+            _ => releases
+                .iter()
+                .find_map(|release| tag_prefix(release.tag_name())),
+        };
+
+        let highest = releases
+            .iter()
+            .filter(|release| tag_prefix(release.tag_name()) == prefix)
+            .filter(|release| self.matches(release.tag_name(), release.is_prerelease()))
+            .filter_map(|release| Some((tag_version(release.tag_name())?, release)))
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, release)| release);
+
+        // tags that aren't versions at all, e.g. `nightly`
+        match self {
+            TagConstraint::LatestPrerelease => highest.or_else(|| releases.first()),
+            _ => highest,
+        }
+    }
+}
+
+/// The same tag with the leading `v` added or removed, e.g. `v1.2.3` for `1.2.3`.
+pub fn alternate_tag(tag: &str) -> Option<String> {
+    match tag.strip_prefix(['v', 'V']) {
+        Some(version) => version
+            .starts_with(|c: char| c.is_ascii_digit())
+            .then(|| version.to_string()),
+        None => tag
+            .starts_with(|c: char| c.is_ascii_digit())
+            .then(|| format!("v{}", tag)),
+    }
+}
+
+/// The part of a tag before its version, without a trailing `v`, e.g. `cli-` for
+/// `cli-v1.2.3` and an empty string for `v1.2.3`.
+pub fn tag_prefix(tag: &str) -> Option<&str> {
+    version_start(tag).map(|start| trim_version_marker(&tag[..start]))
+}
+
+/// Parses the version out of tags such as `1.2.3`, `v1.2`, `cli-v1.2.3` or `2024.01.15`.
+/// Missing minor and patch numbers default to 0.
+pub fn tag_version(tag: &str) -> Option<Version> {
+    let version = &tag[version_start(tag)?..];
+
+    if let Ok(version) = Version::parse(version) {
+        return Some(version);
+    }
+
+    let (core, pre) = match version.split_once(['-', '+']) {
+        Some((core, rest)) if version[core.len()..].starts_with('-') => {
+            (core, rest.split('+').next())
+        }
+        Some((core, _)) => (core, None),
+        None => (version, None),
+    };
+
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let mut version = Version::new(major, minor, patch);
+    if let Some(pre) = pre {
+        version.pre = Prerelease::new(pre).ok()?;
+    }
+    Some(version)
+}
+
+fn version_start(tag: &str) -> Option<usize> {
+    tag.char_indices().find_map(|(i, c)| {
+        let before = tag[..i].chars().next_back();
+        let starts_version = match before {
+            None => true,
+            Some('v' | 'V') => !tag[..i - 1]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_ascii_alphanumeric()),
+            // not the middle of a version, e.g. the `0` of `dev1.0`
+            Some(c) => !c.is_ascii_alphanumeric() && c != '.',
+        };
+        (c.is_ascii_digit() && starts_version).then_some(i)
+    })
+}
+
+/// Strips the `v` off a tag prefix such as `cli-v`, but not off one such as `dev`.
+fn trim_version_marker(prefix: &str) -> &str {
+    match prefix.strip_suffix(['v', 'V']) {
+        Some(rest) if !rest.ends_with(|c: char| c.is_ascii_alphanumeric()) => rest,
+        _ => prefix,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::github::{GithubAsset, GithubRelease};
+
+    fn releases(tags: &[(&str, bool)]) -> Vec<GithubRelease> {
+        tags.iter()
+            .map(|(tag, prerelease)| {
+                serde_json::from_value(json!({
+                    "name": tag,
+                    "tag_name": tag,
+                    "prerelease": prerelease,
+                    "published_at": "2024-01-01T00:00:00Z",
+                    "assets": [],
+                }))
+                .unwrap()
+            })
+            .collect()
+    }
+
+    fn select<'r>(constraint: &str, releases: &'r [GithubRelease]) -> Option<&'r str> {
+        TagConstraint::parse(constraint)
+            .unwrap()
+            .select::<GithubRelease, GithubAsset>(releases)
+            .map(Release::<GithubAsset>::tag_name)
+    }
+
+    fn version(tag: &str) -> Option<String> {
+        tag_version(tag).map(|version| version.to_string())
+    }
+
+    #[test]
+    fn parse_constraints() {
+        assert!(matches!(
+            TagConstraint::parse("nightly").unwrap(),
+            TagConstraint::Exact(tag) if tag == "nightly"
+        ));
+        assert!(matches!(
+            TagConstraint::parse("latest-prerelease").unwrap(),
+            TagConstraint::LatestPrerelease
+        ));
+        assert!(matches!(
+            TagConstraint::parse("^1.4").unwrap(),
+            TagConstraint::Requirement { prefix: None, .. }
+        ));
+        assert!(matches!(
+            TagConstraint::parse(">=0.5,<0.7").unwrap(),
+            TagConstraint::Requirement { prefix: None, .. }
+        ));
+        assert!(matches!(
+            TagConstraint::parse("v~2.0").unwrap(),
+            TagConstraint::Requirement { prefix: None, .. }
+        ));
+        assert!(matches!(
+            TagConstraint::parse("cli-v^2.0").unwrap(),
+            TagConstraint::Requirement { prefix: Some(prefix), .. } if prefix == "cli-"
+        ));
+        assert!(matches!(
+            TagConstraint::parse("foo=bar").unwrap(),
+            TagConstraint::Exact(_)
+        ));
+        assert!(TagConstraint::parse("^x.y").is_err());
+    }
+
+    #[test]
+    fn tag_versions() {
+        assert_eq!(version("1.2.3").as_deref(), Some("1.2.3"));
+        assert_eq!(version("v1.2").as_deref(), Some("1.2.0"));
+        assert_eq!(version("cli-v2.0.1").as_deref(), Some("2.0.1"));
+        assert_eq!(version("release-3").as_deref(), Some("3.0.0"));
+        assert_eq!(version("2024.01.15").as_deref(), Some("2024.1.15"));
+        assert_eq!(version("v1.0.0-rc.1").as_deref(), Some("1.0.0-rc.1"));
+        assert_eq!(version("v1.2-beta+build").as_deref(), Some("1.2.0-beta"));
+        assert_eq!(version("nightly"), None);
+        assert_eq!(version("dev1.0"), None);
+        assert_eq!(version("1.2.3.4"), None);
+    }
+
+    #[test]
+    fn tag_prefixes() {
+        assert_eq!(tag_prefix("v1.2.3"), Some(""));
+        assert_eq!(tag_prefix("1.2.3"), Some(""));
+        assert_eq!(tag_prefix("cli-v2.0"), Some("cli-"));
+        assert_eq!(tag_prefix("release-3"), Some("release-"));
+        assert_eq!(tag_prefix("nightly"), None);
+    }
+
+    #[test]
+    fn alternate_tags() {
+        assert_eq!(alternate_tag("1.2.3").as_deref(), Some("v1.2.3"));
+        assert_eq!(alternate_tag("v1.2.3").as_deref(), Some("1.2.3"));
+        assert_eq!(alternate_tag("nightly"), None);
+        assert_eq!(alternate_tag("vnext"), None);
+    }
+
+    #[test]
+    fn select_exact() {
+        let releases = releases(&[("v1.2.3", false), ("nightly", true)]);
+        assert_eq!(select("v1.2.3", &releases), Some("v1.2.3"));
+        assert_eq!(select("1.2.3", &releases), Some("v1.2.3"));
+        assert_eq!(select("nightly", &releases), Some("nightly"));
+        assert_eq!(select("1.2.4", &releases), None);
+    }
+
+    #[test]
+    fn select_requirement() {
+        let releases = releases(&[
+            ("v2.0.0-rc.1", true),
+            ("v1.5.0", false),
+            ("v1.4.2", false),
+            ("v0.9.0", false),
+        ]);
+        assert_eq!(select("^1.4", &releases), Some("v1.5.0"));
+        assert_eq!(select("~1.4", &releases), Some("v1.4.2"));
+        assert_eq!(select(">=0.5,<1", &releases), Some("v0.9.0"));
+        assert_eq!(select("^2", &releases), None);
+        assert_eq!(select("latest-prerelease", &releases), Some("v2.0.0-rc.1"));
+    }
+
+    #[test]
+    fn select_within_tag_family() {
+        let releases = releases(&[
+            ("cli-v2.1.0", false),
+            ("lib-v3.0.0", false),
+            ("cli-v2.0.0", false),
+            ("lib-v2.5.0", false),
+        ]);
+        // the latest release's family unless one is given
+        assert_eq!(select(">=2", &releases), Some("cli-v2.1.0"));
+        assert_eq!(select("latest-prerelease", &releases), Some("cli-v2.1.0"));
+        assert_eq!(select("lib-^2", &releases), Some("lib-v2.5.0"));
+        assert_eq!(select("lib-v>=2", &releases), Some("lib-v3.0.0"));
+        assert_eq!(select("cli-^3", &releases), None);
+    }
+
+    #[test]
+    fn select_untagged_versions() {
+        let releases = releases(&[("nightly", true), ("stable", false)]);
+        assert_eq!(select("latest-prerelease", &releases), Some("nightly"));
+        assert_eq!(select("^1", &releases), None);
+    }
+}